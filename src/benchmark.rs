@@ -0,0 +1,140 @@
+//! detection speed on a synthetic clip: a static background with a line of white outlined
+//! "letters" that changes every couple of seconds, frames are generated on a separate thread
+//! the same way the decoder works, so only the analysis is measured
+
+use std::sync::mpsc::sync_channel;
+use std::thread;
+use std::time::Instant;
+use crate::detect_still_frames::DetectionParams;
+use crate::frame_pipeline::{get_thread_count, process_frames};
+use crate::frame_time::FrameTime;
+use crate::rgb_frame::RgbFrame;
+
+const FRAME_RATE: u64 = 25;
+
+/// text is shown for that many frames, then hidden for the same number of frames
+const TEXT_DURATION_FRAMES: usize = 50;
+
+const OUTLINE: usize = 2;
+
+/// a small indicator in the corner is shown and hidden every that many frames,
+/// so that --learn-blinking has something to mask
+const BLINK_FRAMES: usize = 6;
+const BLINK_SIZE: usize = 8;
+
+pub struct SyntheticClip {
+    pub width: usize,
+    pub height: usize,
+    pub frames: usize,
+}
+
+fn make_background(width: usize, height: usize) -> Vec<u8> {
+    let mut bitmap = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        for x in 0..width {
+            bitmap.push((x * 160 / width) as u8);
+            bitmap.push((y * 160 / height) as u8);
+            bitmap.push(90);
+        }
+    }
+    return bitmap;
+}
+
+fn fill_rect(bitmap: &mut [u8], width: usize, x: usize, y: usize, w: usize, h: usize, value: u8) {
+    for row in y..y + h {
+        let start = (row * width + x) * 3;
+        for byte in &mut bitmap[start..start + w * 3] {
+            *byte = value;
+        }
+    }
+}
+
+/// a line of letter-like boxes near the bottom, their count depends on the
+/// line number, so that each next line differs from the previous one
+fn draw_text_line(bitmap: &mut [u8], width: usize, height: usize, line: usize) {
+    // sized relative to the frame, so that the change is within the text area limits
+    let letter_height = height / 16;
+    let letter_width = height / 28;
+    let step = letter_width + letter_width / 3 + OUTLINE * 2;
+    let letters = (width * 2 / 3 / step).min(12 + line * 5 % 8);
+    let top = height - height / 6;
+    let left = (width - letters * step) / 2;
+    for letter in 0..letters {
+        let x = left + letter * step;
+        fill_rect(bitmap, width, x - OUTLINE, top - OUTLINE, letter_width + OUTLINE * 2, letter_height + OUTLINE * 2, 0);
+        fill_rect(bitmap, width, x, top, letter_width, letter_height, 255);
+    }
+}
+
+impl SyntheticClip {
+    fn render_frame(&self, background: &[u8], frame_index: usize) -> RgbFrame {
+        let mut bitmap = background.to_vec();
+        let period = frame_index / TEXT_DURATION_FRAMES;
+        if period % 2 == 0 {
+            draw_text_line(&mut bitmap, self.width, self.height, period / 2);
+        }
+        if frame_index / BLINK_FRAMES % 2 == 0 {
+            fill_rect(&mut bitmap, self.width, self.width - BLINK_SIZE * 3, BLINK_SIZE * 2, BLINK_SIZE, BLINK_SIZE, 200);
+        }
+        let millis = frame_index as u64 * 1000 / FRAME_RATE;
+        let time = FrameTime { frame_index, pts: Some(frame_index as i64), millis };
+        return RgbFrame::new(time, self.width, self.height, bitmap);
+    }
+
+    /// indexes of frames detected as text changes and the time it took
+    fn detect(&self, params: &DetectionParams) -> (Vec<usize>, f64) {
+        let (frame_sender, frame_receiver) = sync_channel(16);
+        let width = self.width;
+        let height = self.height;
+        let clip = SyntheticClip { width, height, frames: self.frames };
+        let generating = thread::spawn(move || {
+            let background = make_background(width, height);
+            for frame_index in 0..clip.frames {
+                if frame_sender.send(clip.render_frame(&background, frame_index)).is_err() {
+                    break;
+                }
+            }
+        });
+        let started = Instant::now();
+        let mut text_changes = Vec::new();
+        process_frames(frame_receiver.into_iter(), params, &mut |change| {
+            if change.is_text_change {
+                text_changes.push(change.time.frame_index);
+            }
+        });
+        let seconds = started.elapsed().as_secs_f64();
+        generating.join().unwrap();
+        return (text_changes, seconds);
+    }
+}
+
+/// run the detection on one thread and on the given number of threads and print both speeds
+pub fn run_benchmark(clip: &SyntheticClip, threads: usize) {
+    let threads = get_thread_count(threads);
+    let single = DetectionParams { threads: 1, ..DetectionParams::default() };
+    let parallel = DetectionParams { threads, ..DetectionParams::default() };
+    println!("Synthetic clip of {} frames {}x{}", clip.frames, clip.width, clip.height);
+
+    let (single_changes, single_seconds) = clip.detect(&single);
+    println!(
+        "  1 thread: {:.2} s, {:.1} fps, {} text changes",
+        single_seconds, clip.frames as f64 / single_seconds, single_changes.len()
+    );
+    let (parallel_changes, parallel_seconds) = clip.detect(&parallel);
+    println!(
+        "  {} threads: {:.2} s, {:.1} fps, {} text changes",
+        threads, parallel_seconds, clip.frames as f64 / parallel_seconds, parallel_changes.len()
+    );
+    println!("  speedup: {:.2}x", single_seconds / parallel_seconds);
+    if single_changes != parallel_changes {
+        eprintln!("  text changes differ between single and multi-threaded runs!");
+    }
+
+    // the mask learned mid-run must not depend on how many frames were in flight at the moment
+    let (single_changes, _) = clip.detect(&DetectionParams { learn_blinking: true, ..single });
+    let (parallel_changes, _) = clip.detect(&DetectionParams { learn_blinking: true, ..parallel });
+    println!("  with blinking learned: {} text changes", parallel_changes.len());
+    if single_changes != parallel_changes {
+        eprintln!("  text changes with blinking learned differ between single and multi-threaded runs!");
+    }
+}
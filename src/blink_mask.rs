@@ -0,0 +1,147 @@
+//! learns small parts of the frame that toggle periodically through the whole video, like
+//! the auto-play indicator, so that they could be excluded from the change detection
+
+use crate::pixel_utils::Point;
+use crate::rel_matrix::Bounds;
+
+/// frame is split into square blocks of that many pixels, the mask is learned per block
+pub const BLOCK_SIZE: usize = 16;
+
+/// change touching more blocks than that is not a UI element blinking
+const MAX_BLINK_BLOCKS: usize = 6;
+
+/// block has to change at least that many times on its own before it is masked
+const MIN_TOGGLES: usize = 8;
+
+/// how much intervals between toggles may deviate from their mean: a blinking element
+/// changes with the same period, while a text box just happens to change sometimes
+const MAX_PERIOD_DEVIATION: f64 = 0.2;
+
+pub struct BlinkLearner {
+    cols: usize,
+    rows: usize,
+    /// for each block, frame indices of last MIN_TOGGLES isolated changes
+    toggles: Vec<Vec<usize>>,
+    masked: Vec<bool>,
+}
+
+/// blink may stay on and off for different number of frames, but on -> off -> on
+/// always takes the same time, so intervals are taken between every second toggle
+fn is_periodic(toggles: &[usize]) -> bool {
+    let periods: Vec<f64> = (2..toggles.len())
+        .map(|i| (toggles[i] - toggles[i - 2]) as f64)
+        .collect();
+    if periods.is_empty() {
+        return false;
+    }
+    let mean = periods.iter().sum::<f64>() / periods.len() as f64;
+    let variance = periods.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / periods.len() as f64;
+    return mean > 0.0 && variance.sqrt() / mean <= MAX_PERIOD_DEVIATION;
+}
+
+impl BlinkLearner {
+    pub fn new(width: usize, height: usize) -> BlinkLearner {
+        let cols = (width + BLOCK_SIZE - 1) / BLOCK_SIZE;
+        let rows = (height + BLOCK_SIZE - 1) / BLOCK_SIZE;
+        return BlinkLearner {
+            cols,
+            rows,
+            toggles: vec![Vec::new(); cols * rows],
+            masked: vec![false; cols * rows],
+        };
+    }
+
+    pub fn is_masked(&self, point: &Point) -> bool {
+        let col = point.x as usize / BLOCK_SIZE;
+        let row = point.y as usize / BLOCK_SIZE;
+        return col < self.cols && row < self.rows && self.masked[row * self.cols + col];
+    }
+
+    /// block_points_changed is the number of changed pixels in each block of the frame,
+    /// returns true if some new block got masked, so the pixel mask has to be rebuilt
+    pub fn learn(&mut self, frame_index: usize, block_points_changed: &[u32]) -> bool {
+        let changed_blocks: Vec<usize> = block_points_changed.iter()
+            .enumerate()
+            .filter(|(block, points)| **points > 0 && !self.masked[*block])
+            .map(|(block, _)| block)
+            .collect();
+        if changed_blocks.is_empty() || changed_blocks.len() > MAX_BLINK_BLOCKS {
+            return false;
+        }
+        let mut got_masked = false;
+        for block in changed_blocks {
+            let toggles = &mut self.toggles[block];
+            toggles.push(frame_index);
+            if toggles.len() > MIN_TOGGLES {
+                toggles.remove(0);
+            }
+            if toggles.len() == MIN_TOGGLES && is_periodic(toggles) {
+                self.masked[block] = true;
+                got_masked = true;
+            }
+        }
+        return got_masked;
+    }
+
+    pub fn get_masked_blocks(&self) -> Vec<Bounds> {
+        return (0..self.masked.len())
+            .filter(|block| self.masked[*block])
+            .map(|block| {
+                let x = (block % self.cols * BLOCK_SIZE) as i64;
+                let y = (block / self.cols * BLOCK_SIZE) as i64;
+                Bounds {
+                    start: Point { x, y },
+                    end: Point { x: x + BLOCK_SIZE as i64 - 1, y: y + BLOCK_SIZE as i64 - 1 },
+                }
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4x3 blocks
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 48;
+
+    fn changed_blocks(blocks: &[usize]) -> Vec<u32> {
+        let mut block_points_changed = vec![0; 12];
+        for block in blocks {
+            block_points_changed[*block] = 20;
+        }
+        return block_points_changed;
+    }
+
+    #[test]
+    fn masks_block_toggling_periodically() {
+        let mut learner = BlinkLearner::new(WIDTH, HEIGHT);
+        // on for 3 frames, off for 5
+        let toggles: Vec<usize> = (0..4).flat_map(|cycle| vec![cycle * 8, cycle * 8 + 3]).collect();
+        let got_masked: Vec<bool> = toggles.iter().map(|frame_index| learner.learn(*frame_index, &changed_blocks(&[5]))).collect();
+        assert_eq!(got_masked, vec![false, false, false, false, false, false, false, true]);
+        assert!(learner.is_masked(&Point { x: 20, y: 16 }));
+        assert!(!learner.is_masked(&Point { x: 0, y: 0 }));
+        let masked_blocks = learner.get_masked_blocks();
+        assert_eq!(masked_blocks.len(), 1);
+        assert_eq!(masked_blocks[0].start, Point { x: 16, y: 16 });
+    }
+
+    #[test]
+    fn irregular_changes_are_not_masked() {
+        let mut learner = BlinkLearner::new(WIDTH, HEIGHT);
+        for frame_index in [0, 3, 20, 21, 50, 90, 93, 140, 141, 200].iter() {
+            assert!(!learner.learn(*frame_index, &changed_blocks(&[5])));
+        }
+    }
+
+    #[test]
+    fn large_changes_are_not_blinks() {
+        let mut learner = BlinkLearner::new(WIDTH, HEIGHT);
+        for frame_index in 0..20 {
+            assert!(!learner.learn(frame_index * 4, &changed_blocks(&[0, 1, 2, 3, 4, 5, 6])));
+        }
+        assert!(learner.get_masked_blocks().is_empty());
+    }
+}
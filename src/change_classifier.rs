@@ -0,0 +1,119 @@
+//! tells a real text change from a video "quality jump" by the way the change is spread
+//! over the frame rather than by the absolute number of changed pixels: quality jump
+//! is a diffuse low-amplitude change all over the frame, while new text is dense
+//! high-contrast change in glyph-shaped blobs, all thresholds are relative to
+//! the frame size, so that 720p and 4K recordings are classified the same way
+
+use serde::Serialize;
+
+/// tuned on a 1280x720 recording, where that was 15000 points: shortest line of text
+const MIN_TEXT_AREA: f64 = 0.016;
+
+/// tuned on a 1280x720 recording, where that was 75000 points: lengthy text is usually
+/// about 50k pixels, everything larger is a background change or a scene cut
+const MAX_TEXT_AREA: f64 = 0.08;
+
+/// text occupies a panel or a couple of lines, quality jump touches most of the frame
+const MAX_TEXT_SPREAD: f64 = 0.35;
+
+/// mean summed R,G,B change of changed points in range [0..1], white letters with black
+/// outline appearing on a dark panel give 0.5 and more, quality jump is barely above noise
+const MIN_TEXT_AMPLITUDE: f64 = 0.12;
+
+/// portion of changed points among all points of touched blocks, glyph strokes fill a good
+/// part of a block they pass through, quality jump sprinkles single points here and there
+const MIN_TEXT_DENSITY: f64 = 0.1;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// too small to be a text, like a blinking cursor or a sprite blinking eyes
+    Minor,
+    Text,
+    /// the same picture re-encoded with different quality
+    QualityJump,
+    /// too large to be a text, like a background change
+    Large,
+}
+
+/// numbers describing how the change is spread over the frame
+#[derive(Debug, Copy, Clone)]
+pub struct ChangeShape {
+    /// changed points relative to the frame size
+    pub area: f64,
+    /// blocks with changed points relative to all blocks of the frame
+    pub spread: f64,
+    /// mean change of changed points in range [0..1]
+    pub amplitude: f64,
+    /// changed points relative to all points of blocks with changed points
+    pub density: f64,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ClassifierParams {
+    pub min_text_area: f64,
+    pub max_text_area: f64,
+}
+
+impl Default for ClassifierParams {
+    fn default() -> ClassifierParams {
+        return ClassifierParams {
+            min_text_area: MIN_TEXT_AREA,
+            max_text_area: MAX_TEXT_AREA,
+        };
+    }
+}
+
+pub fn classify_change(shape: &ChangeShape, params: &ClassifierParams) -> ChangeKind {
+    if shape.area < params.min_text_area {
+        return ChangeKind::Minor;
+    }
+    if shape.amplitude < MIN_TEXT_AMPLITUDE {
+        // low contrast change, no matter how large, nobody draws text like that
+        return ChangeKind::QualityJump;
+    }
+    if shape.spread > MAX_TEXT_SPREAD && shape.density < MIN_TEXT_DENSITY {
+        return ChangeKind::QualityJump;
+    }
+    if shape.area > params.max_text_area || shape.spread > MAX_TEXT_SPREAD {
+        return ChangeKind::Large;
+    }
+    if shape.density < MIN_TEXT_DENSITY {
+        return ChangeKind::QualityJump;
+    }
+    return ChangeKind::Text;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT_SHAPE: ChangeShape = ChangeShape { area: 0.04, spread: 0.15, amplitude: 0.5, density: 0.3 };
+
+    #[test]
+    fn dense_contrast_change_is_text() {
+        assert_eq!(classify_change(&TEXT_SHAPE, &ClassifierParams::default()), ChangeKind::Text);
+    }
+
+    #[test]
+    fn small_change_is_minor() {
+        let shape = ChangeShape { area: 0.001, ..TEXT_SHAPE };
+        assert_eq!(classify_change(&shape, &ClassifierParams::default()), ChangeKind::Minor);
+    }
+
+    #[test]
+    fn faint_or_sparse_change_is_quality_jump() {
+        let faint = ChangeShape { amplitude: 0.05, ..TEXT_SHAPE };
+        assert_eq!(classify_change(&faint, &ClassifierParams::default()), ChangeKind::QualityJump);
+        let sprinkled = ChangeShape { area: 0.05, spread: 0.9, density: 0.02, ..TEXT_SHAPE };
+        assert_eq!(classify_change(&sprinkled, &ClassifierParams::default()), ChangeKind::QualityJump);
+    }
+
+    #[test]
+    fn widespread_or_huge_change_is_large() {
+        let widespread = ChangeShape { spread: 0.6, ..TEXT_SHAPE };
+        assert_eq!(classify_change(&widespread, &ClassifierParams::default()), ChangeKind::Large);
+        let huge = ChangeShape { area: 0.3, ..TEXT_SHAPE };
+        assert_eq!(classify_change(&huge, &ClassifierParams::default()), ChangeKind::Large);
+    }
+}
@@ -0,0 +1,200 @@
+//! characters the letters of a frame are compared to: every char is rendered in the
+//! font and matched against every letter, so the set should be no bigger than the
+//! hardsubs need, named presets cover the common cases, Unicode ranges and custom
+//! strings cover the rest
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use crate::error::{Error, Result};
+
+const LETTERS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const PUNCTUATION: &str = ".,?!'\"-:;()…";
+
+const HIRAGANA: (u32, u32) = (0x3041, 0x3096);
+const KATAKANA: (u32, u32) = (0x30A1, 0x30FA);
+/// iteration marks and the long vowel mark
+const KANA_MARKS: &str = "ゝゞヽヾー";
+const JAPANESE_PUNCTUATION: &str = "、。「」『』（）【】！？…・〜～：；";
+const FULLWIDTH_DIGITS: (u32, u32) = (0xFF10, 0xFF19);
+
+/// letters of Western European languages, the × and ÷ of the same Unicode block are left out
+const ACCENTED: &str = "ÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏÐÑÒÓÔÕÖØÙÚÛÜÝÞßàáâãäåæçèéêëìíîïðñòóôõöøùúûüýþÿŒœ";
+
+/// kanji are not in any preset: there are thousands of them, each one rendered and compared,
+/// so they are better given as a list, like the Jōyō kanji or the script of the game
+pub const PRESET_NAMES: &[&str] = &[
+    "basic", "letters", "digits", "punctuation", "latin", "accented", "latin-accented",
+    "hiragana", "katakana", "kana", "japanese",
+];
+
+/// the default preset, the letter set the OCR always had, digits and the rest of
+/// the punctuation are opt-in, since they are confused with letters, like "l" and "1"
+pub const DEFAULT_PRESET: &str = "basic";
+
+/// the default preset of the Japanese mode
+pub const JAPANESE_PRESET: &str = "japanese";
+
+/// every char of a range gets rendered and compared to each letter, the CJK Unified
+/// Ideographs block U+4E00-U+9FFF fits, the whole of Unicode does not
+const MAX_RANGE_CHARS: u32 = 0x8000;
+
+fn get_range(range: (u32, u32)) -> String {
+    return (range.0..=range.1).filter_map(std::char::from_u32).collect();
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Charset {
+    chars: Vec<char>,
+}
+
+impl Charset {
+    pub fn new() -> Charset {
+        return Charset { chars: Vec::new() };
+    }
+
+    pub fn from_chars(chars: &str) -> Charset {
+        let mut charset = Charset::new();
+        charset.add_chars(chars.chars());
+        return charset;
+    }
+
+    /// None if there is no such preset, see PRESET_NAMES
+    pub fn preset(name: &str) -> Option<Charset> {
+        let chars = match name {
+            // what the OCR was limited to originally
+            "basic" => format!("{},.", LETTERS),
+            "letters" => LETTERS.to_string(),
+            "digits" => DIGITS.to_string(),
+            "punctuation" => PUNCTUATION.to_string(),
+            "latin" => format!("{}{}{}", LETTERS, DIGITS, PUNCTUATION),
+            "accented" => ACCENTED.to_string(),
+            "latin-accented" => format!("{}{}{}{}", LETTERS, DIGITS, PUNCTUATION, ACCENTED),
+            "hiragana" => get_range(HIRAGANA),
+            "katakana" => get_range(KATAKANA),
+            "kana" => format!("{}{}{}", get_range(HIRAGANA), get_range(KATAKANA), KANA_MARKS),
+            "japanese" => format!(
+                "{}{}{}{}{}",
+                get_range(HIRAGANA), get_range(KATAKANA), KANA_MARKS, JAPANESE_PUNCTUATION, get_range(FULLWIDTH_DIGITS)
+            ),
+            _ => return None,
+        };
+        return Some(Charset::from_chars(&chars));
+    }
+
+    /// a preset name, a Unicode range like "U+0410-U+044F" or a single code point like "U+00A9"
+    pub fn parse(spec: &str) -> Result<Charset> {
+        if let Some(charset) = Charset::preset(spec) {
+            return Ok(charset);
+        }
+        let (start, end) = match spec.find('-') {
+            Some(dash) => (&spec[..dash], &spec[dash + 1..]),
+            None => (spec, spec),
+        };
+        let (start, end) = match (parse_code_point(start), parse_code_point(end)) {
+            (Some(start), Some(end)) if start <= end => (start, end),
+            _ => return Err(Error::Input(format!(
+                "Invalid charset {:?}, expected one of {} or a Unicode range like U+0410-U+044F",
+                spec, PRESET_NAMES.join(", ")
+            ))),
+        };
+        if end - start >= MAX_RANGE_CHARS {
+            return Err(Error::Input(format!(
+                "Charset range {} has {} code points, at most {} can be recognized at once",
+                spec, end - start + 1, MAX_RANGE_CHARS
+            )));
+        }
+        let mut charset = Charset::new();
+        charset.add_chars((start..=end).filter_map(std::char::from_u32));
+        return Ok(charset);
+    }
+
+    /// every char of a text file, like a list of kanji or the script of the game, UTF-8
+    pub fn from_file(path: &Path) -> Result<Charset> {
+        let text = fs::read_to_string(path)
+            .map_err(Error::io(format!("Failed to read charset file {}", path.display())))?;
+        return Ok(Charset::from_chars(&text));
+    }
+
+    /// chars that are already in the set are skipped, so the order of the first appearance is kept
+    pub fn add_chars(&mut self, chars: impl IntoIterator<Item = char>) {
+        let mut known: HashSet<char> = self.chars.iter().cloned().collect();
+        for char in chars {
+            if !char.is_whitespace() && known.insert(char) {
+                self.chars.push(char);
+            }
+        }
+    }
+
+    pub fn extend(&mut self, other: &Charset) {
+        self.add_chars(other.chars.iter().cloned());
+    }
+
+    /// Latin letters and digits that take half of a full-width cell, the Japanese
+    /// mode would cut two of them as one char, full-width ones like "Ａ" are fine
+    pub fn get_half_width_letters(&self) -> Vec<char> {
+        return self.chars.iter().cloned().filter(|char| char.is_ascii_alphanumeric()).collect();
+    }
+
+    /// the removed chars are returned
+    pub fn remove_half_width_letters(&mut self) -> Vec<char> {
+        let removed = self.get_half_width_letters();
+        self.chars.retain(|char| !char.is_ascii_alphanumeric());
+        return removed;
+    }
+
+    pub fn get_chars(&self) -> &[char] {
+        return &self.chars;
+    }
+}
+
+impl Default for Charset {
+    fn default() -> Charset {
+        return Charset::preset(DEFAULT_PRESET).unwrap();
+    }
+}
+
+/// "U+0410" or "u+410"
+fn parse_code_point(text: &str) -> Option<u32> {
+    let text = text.trim();
+    let hex = text.strip_prefix("U+").or_else(|| text.strip_prefix("u+"))?;
+    return u32::from_str_radix(hex, 16).ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_presets_and_ranges() {
+        assert_eq!(Charset::parse("latin").unwrap(), Charset::preset("latin").unwrap());
+        assert_eq!(Charset::parse("U+0041-U+0043").unwrap().get_chars(), &['A', 'B', 'C']);
+        assert_eq!(Charset::parse("u+00A9").unwrap().get_chars(), &['©']);
+        // surrogates are not chars, they are skipped
+        assert_eq!(Charset::parse("U+D7FF-U+E000").unwrap().get_chars(), &['\u{D7FF}', '\u{E000}']);
+        assert_eq!(Charset::parse("U+4E00-U+9FFF").unwrap().get_chars().len(), 0x5200);
+    }
+
+    #[test]
+    fn default_is_letters_with_comma_and_period() {
+        let default = Charset::default();
+        assert_eq!(default.get_chars().len(), 54);
+        assert!(default.get_chars().contains(&','));
+        assert!(!default.get_chars().contains(&'"'));
+    }
+
+    #[test]
+    fn rejects_malformed_specs() {
+        for spec in ["", "latn", "0041", "U+", "U+ZZZZ", "U+0043-U+0041", "U+0041-", "U+0000-U+10FFFF"].iter() {
+            assert!(Charset::parse(spec).is_err(), "{:?} should be rejected", spec);
+        }
+    }
+
+    #[test]
+    fn removes_half_width_letters_only() {
+        let mut charset = Charset::from_chars("aZ9あＡ。,");
+        assert_eq!(charset.remove_half_width_letters(), vec!['a', 'Z', '9']);
+        assert_eq!(charset.get_chars(), &['あ', 'Ａ', '。', ',']);
+    }
+}
@@ -0,0 +1,221 @@
+//! segmentation of Japanese text: kana and kanji are often made of several strokes that do
+//! not touch or even overlap, so instead of taking every connected piece for a letter, pieces
+//! are grouped into lines and each line is cut into square cells of the font size, the way
+//! full-width text is laid out, ruby (furigana written small above kanji) gets lines of its own
+
+use crate::font_data::FontData;
+use crate::ocr_out_from_image::merge_char_parts;
+use crate::rel_matrix::{extend_bounds, Bounds, RelMatrix};
+
+/// a line lower than that portion of the text height can be ruby...
+const MAX_RUBY_HEIGHT_PORTION: f32 = 0.7;
+/// ...if it is closer than that portion of the text height to the line below it
+const MAX_RUBY_GAP_PORTION: f32 = 0.5;
+
+/// relative to the cell width, a piece that starts further from the first piece of a
+/// char belongs to the next char, one that ends further does not fit in the cell
+const MAX_PIECE_START_PORTION: f32 = 0.8;
+const MAX_PIECE_END_PORTION: f32 = 1.05;
+
+/// how letters are cut out of a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segmentation {
+    /// every connected piece is a letter, with dots and diacritics merged in, for Latin scripts
+    Letters,
+    /// lines are cut into full-width cells, for Japanese, ruby is either left out or
+    /// recognized as a line of its own, scaled up to the size of the font
+    FullWidthCells { ignore_ruby: bool },
+}
+
+impl Default for Segmentation {
+    fn default() -> Segmentation {
+        return Segmentation::Letters;
+    }
+}
+
+pub struct PieceLine {
+    pub pieces: Vec<RelMatrix>,
+    /// box around all the pieces
+    pub bounds: Bounds,
+}
+
+/// chars of a line, left to right
+pub struct CellLine {
+    pub cells: Vec<RelMatrix>,
+    pub is_ruby: bool,
+    /// size of the text relative to the font size, below 1 for ruby
+    pub size_ratio: f32,
+}
+
+/// relative to the text height, a line can not be taller than that, so strokes of a kanji
+/// like 三 make one line while ruby right above the text makes a line of its own
+const MAX_LINE_HEIGHT_PORTION: f32 = 1.1;
+
+fn merge_lines(lines: Vec<PieceLine>) -> PieceLine {
+    let mut bounds = lines[0].bounds;
+    let mut pieces = Vec::new();
+    for line in lines {
+        bounds = extend_bounds(Some(extend_bounds(Some(bounds), line.bounds.start)), line.bounds.end);
+        pieces.extend(line.pieces);
+    }
+    return PieceLine { pieces, bounds };
+}
+
+/// bands that do not fit in one line are split at the widest gap between them, the gap
+/// between lines of text or between ruby and the text is wider than between strokes of a char
+fn split_at_gaps(mut bands: Vec<PieceLine>, max_line_height: i64, lines: &mut Vec<PieceLine>) {
+    let height = bands.last().unwrap().bounds.end.y - bands[0].bounds.start.y + 1;
+    if bands.len() == 1 || height <= max_line_height {
+        lines.push(merge_lines(bands));
+        return;
+    }
+    let split_at = (1..bands.len())
+        .max_by_key(|i| bands[*i].bounds.start.y - bands[*i - 1].bounds.end.y)
+        .unwrap();
+    let lower_bands = bands.split_off(split_at);
+    split_at_gaps(bands, max_line_height, lines);
+    split_at_gaps(lower_bands, max_line_height, lines);
+}
+
+/// pieces whose vertical spans overlap, directly or through other pieces, make a band,
+/// bands are joined into lines no taller than the text height, top to bottom
+pub fn group_pieces_by_line(mut pieces: Vec<RelMatrix>, text_height: f32) -> Vec<PieceLine> {
+    pieces.sort_by_key(|piece| piece.bounds.start.y);
+    let mut bands: Vec<PieceLine> = Vec::new();
+    for piece in pieces {
+        match bands.last_mut() {
+            Some(band) if piece.bounds.start.y <= band.bounds.end.y => {
+                band.bounds = extend_bounds(Some(extend_bounds(Some(band.bounds), piece.bounds.start)), piece.bounds.end);
+                band.pieces.push(piece);
+            },
+            _ => bands.push(PieceLine { bounds: piece.bounds, pieces: vec![piece] }),
+        }
+    }
+    let mut lines = Vec::new();
+    if !bands.is_empty() {
+        let max_line_height = (text_height * MAX_LINE_HEIGHT_PORTION).round() as i64;
+        split_at_gaps(bands, max_line_height, &mut lines);
+    }
+    return lines;
+}
+
+/// text height for when the font size is not known yet: most kanji and many kana
+/// strokes are about as wide as the cell, so the widest pieces tell it, but for
+/// a few that span several chars, like a long vowel mark touching the next one
+pub fn guess_text_height(pieces: &[RelMatrix]) -> f32 {
+    let mut widths: Vec<usize> = pieces.iter().map(|piece| piece.bounds.get_width()).collect();
+    if widths.is_empty() {
+        return 0.0;
+    }
+    widths.sort_unstable();
+    return widths[(widths.len() - 1) * 9 / 10] as f32;
+}
+
+/// a line much lower than the text, right above a line of the normal height and not sticking out of it
+fn is_ruby(line: &PieceLine, next_line: Option<&PieceLine>, text_height: f32) -> bool {
+    let next_line = match next_line {
+        Some(next_line) => next_line,
+        None => return false,
+    };
+    let max_ruby_height = text_height * MAX_RUBY_HEIGHT_PORTION;
+    let gap = (next_line.bounds.start.y - line.bounds.end.y) as f32;
+    return (line.bounds.get_height() as f32) < max_ruby_height
+        && next_line.bounds.get_height() as f32 >= max_ruby_height
+        && gap < text_height * MAX_RUBY_GAP_PORTION
+        && line.bounds.start.x as f32 >= next_line.bounds.start.x as f32 - text_height
+        && line.bounds.end.x as f32 <= next_line.bounds.end.x as f32 + text_height;
+}
+
+/// pieces of a line, left to right: a char starts with the leftmost piece that is not taken yet
+/// and takes the next pieces while they start within most of a cell from it and fit in the cell
+pub fn cut_into_cells(mut pieces: Vec<RelMatrix>, cell_width: f32) -> Vec<RelMatrix> {
+    pieces.sort_by_key(|piece| piece.bounds.start.x);
+    let max_start = (cell_width * MAX_PIECE_START_PORTION).round() as i64;
+    let max_end = (cell_width * MAX_PIECE_END_PORTION).round() as i64;
+    let mut cells: Vec<RelMatrix> = Vec::new();
+    let mut char_start_x = 0;
+    for piece in pieces {
+        let fits = !cells.is_empty()
+            && piece.bounds.start.x < char_start_x + max_start
+            && piece.bounds.end.x < char_start_x + max_end;
+        if fits {
+            let cell = cells.pop().unwrap();
+            cells.push(merge_char_parts(&cell, &piece));
+        } else {
+            char_start_x = piece.bounds.start.x;
+            cells.push(piece);
+        }
+    }
+    return cells;
+}
+
+/// every line of the frame cut into chars of the font size, lines of ruby are either
+/// left out or cut into smaller cells of their own size, top to bottom
+pub fn segment_cell_lines(pieces: Vec<RelMatrix>, font_data: &FontData, ignore_ruby: bool) -> Vec<CellLine> {
+    let text_height = font_data.get_text_height();
+    let lines = group_pieces_by_line(pieces, text_height);
+    let ruby_flags: Vec<bool> = (0..lines.len())
+        .map(|i| is_ruby(&lines[i], lines.get(i + 1), text_height))
+        .collect();
+    let mut cell_lines = Vec::new();
+    for (line, is_ruby) in lines.into_iter().zip(ruby_flags) {
+        if is_ruby && ignore_ruby {
+            continue;
+        }
+        let size_ratio = if is_ruby { line.bounds.get_height() as f32 / text_height } else { 1.0 };
+        let cells = cut_into_cells(line.pieces, font_data.get_cell_width() * size_ratio);
+        cell_lines.push(CellLine { cells, is_ruby, size_ratio });
+    }
+    return cell_lines;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixel_utils::Point;
+
+    fn piece(start_x: i64, start_y: i64, end_x: i64, end_y: i64) -> RelMatrix {
+        let bounds = Bounds { start: Point { x: start_x, y: start_y }, end: Point { x: end_x, y: end_y } };
+        return RelMatrix { bitmap: vec![vec![1.0; bounds.get_height()]; bounds.get_width()], bounds };
+    }
+
+    fn get_x_spans(cells: &[RelMatrix]) -> Vec<(i64, i64)> {
+        return cells.iter().map(|cell| (cell.bounds.start.x, cell.bounds.end.x)).collect();
+    }
+
+    #[test]
+    fn strokes_of_one_char_make_one_cell() {
+        // 川 of three strokes, then 二 of two, in cells 20 px wide
+        let pieces = vec![
+            piece(42, 0, 57, 4), piece(2, 0, 4, 19), piece(9, 2, 11, 16), piece(15, 0, 17, 19),
+            piece(20, 2, 37, 4), piece(21, 15, 38, 17),
+        ];
+        let cells = cut_into_cells(pieces, 20.0);
+        assert_eq!(get_x_spans(&cells), vec![(2, 17), (20, 38), (42, 57)]);
+        assert_eq!(cells[0].bounds.get_height(), 20);
+    }
+
+    #[test]
+    fn piece_sticking_out_of_the_cell_starts_the_next_one() {
+        // a comma right after a narrow char still fits, a long vowel mark does not
+        let cells = cut_into_cells(vec![piece(0, 0, 6, 19), piece(10, 16, 13, 19), piece(14, 8, 35, 10)], 20.0);
+        assert_eq!(get_x_spans(&cells), vec![(0, 13), (14, 35)]);
+    }
+
+    #[test]
+    fn ruby_above_text_is_a_line_of_its_own() {
+        let pieces = vec![piece(0, 0, 9, 9), piece(12, 0, 21, 9), piece(0, 13, 19, 32), piece(24, 12, 43, 32)];
+        let lines = group_pieces_by_line(pieces, 20.0);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].pieces.len(), 2);
+        assert!(is_ruby(&lines[0], lines.get(1), 20.0));
+        assert!(!is_ruby(&lines[1], None, 20.0));
+    }
+
+    #[test]
+    fn text_height_is_told_by_the_widest_pieces() {
+        let mut pieces: Vec<RelMatrix> = (0..9).map(|i| piece(i * 30, 0, i * 30 + 19, 19)).collect();
+        pieces.push(piece(0, 30, 59, 32));
+        assert_eq!(guess_text_height(&pieces), 20.0);
+    }
+}
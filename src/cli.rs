@@ -0,0 +1,634 @@
+//! the detect-still-frames command line, each subcommand parses its args into the
+//! params of the library and calls the matching entry point, the binary only calls
+//! run_cli(), so that the entry points it needs stay internal
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use crate::benchmark::{run_benchmark, SyntheticClip};
+use crate::change_classifier::ClassifierParams;
+use crate::charset::{Charset, JAPANESE_PRESET};
+use crate::cjk_segmentation::Segmentation;
+use crate::detect_still_frames::{detect_still_frames, DetectionParams};
+use crate::error::{Error, Result};
+use crate::font_data::{FontData, DEFAULT_SCALE};
+use crate::font_identification::{load_candidates, FontCandidate, FontSelection};
+use crate::font_search::{find_font, get_system_font_dirs, FontLocation};
+use crate::frame_region::{parse_rect, FrameRegion, IgnoreMask};
+use crate::frame_time::{parse_timestamp, RangeBound, TimeRange};
+use crate::image_sequence::ImageTiming;
+use crate::live_ocr::live_ocr;
+use crate::ocr_out_from_image::ocr_out_from_image;
+use crate::pipe_input::{is_pipe_input, PipeFormat, PipeInput};
+use crate::ppm_format::decode as decode_ppm;
+use crate::rel_matrix::Bounds;
+use crate::text_region_heatmap::detect_text_region;
+use crate::timeline::load_text_regions;
+use crate::video_decoding::InputParams;
+use crate::video_to_srt::video_to_srt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn threshold_args() -> Vec<Arg<'static, 'static>> {
+    return vec![
+        Arg::with_name("pixel-noise-threshold")
+            .long("pixel-noise-threshold")
+            .takes_value(true)
+            .help("Summed change of R,G,B values of a pixel to be considered changed [default: 20]"),
+        Arg::with_name("change-factor-threshold")
+            .long("change-factor-threshold")
+            .takes_value(true)
+            .help("Minimal change factor in range [0..1] of a text change frame [default: 0.001]"),
+        Arg::with_name("min-text-area")
+            .long("min-text-area")
+            .takes_value(true)
+            .help("Changes of smaller portion of the frame are too small to be a text [default: 0.016]"),
+        Arg::with_name("max-text-area")
+            .long("max-text-area")
+            .takes_value(true)
+            .help("Changes of larger portion of the frame are too large to be a text [default: 0.08]"),
+        Arg::with_name("settle-frames")
+            .long("settle-frames")
+            .takes_value(true)
+            .help("For text revealed letter by letter: wait till text stays unchanged for that \
+                many frames and take that frame instead of the first one [default: 0 - do not wait]"),
+        Arg::with_name("learn-blinking")
+            .long("learn-blinking")
+            .help("Exclude small parts of the frame that are found to change periodically, \
+                like the auto-play indicator, learned blocks are saved in the timeline"),
+    ];
+}
+
+fn threads_arg() -> Arg<'static, 'static> {
+    return Arg::with_name("threads")
+        .long("threads")
+        .takes_value(true)
+        .help("Number of threads comparing frames, the video is decoded on one more thread \
+            [default: 0 - one per CPU core]");
+}
+
+fn timeline_all_frames_arg() -> Arg<'static, 'static> {
+    return Arg::with_name("timeline-all-frames")
+        .long("timeline-all-frames")
+        .help("List every frame that differs from the previous one in the timeline, not only text changes, \
+            scene changes and changes too big to be minor");
+}
+
+fn range_args() -> Vec<Arg<'static, 'static>> {
+    return vec![
+        Arg::with_name("start")
+            .long("start")
+            .takes_value(true)
+            .help("Seek to [[hours:]minutes:]seconds before decoding, frame indices and times in \
+                the output are still counted from the file start"),
+        Arg::with_name("end")
+            .long("end")
+            .takes_value(true)
+            .help("Stop decoding at [[hours:]minutes:]seconds"),
+        Arg::with_name("start-frame")
+            .long("start-frame")
+            .takes_value(true)
+            .conflicts_with("start")
+            .help("Start at the frame of that index, frames before it are decoded and dropped instead of seeking, \
+                so that the index is exact on a variable frame rate video too"),
+        Arg::with_name("end-frame")
+            .long("end-frame")
+            .takes_value(true)
+            .conflicts_with("end")
+            .help("Same as --end, but given as a frame index, this frame is not processed"),
+    ];
+}
+
+fn parse_range_bound(matches: &ArgMatches, time_name: &str, frame_name: &str) -> Option<RangeBound> {
+    if let Some(value) = matches.value_of(time_name) {
+        let millis = parse_timestamp(value).unwrap_or_else(|error| {
+            eprintln!("Invalid value of --{}: {}", time_name, error);
+            std::process::exit(1);
+        });
+        return Some(RangeBound::Millis(millis));
+    }
+    return matches.value_of(frame_name).map(|_| RangeBound::Frame(parse_value(matches, frame_name, 0)));
+}
+
+fn parse_range(matches: &ArgMatches) -> TimeRange {
+    return TimeRange {
+        start: parse_range_bound(matches, "start", "start-frame"),
+        end: parse_range_bound(matches, "end", "end-frame"),
+    };
+}
+
+fn image_args() -> Vec<Arg<'static, 'static>> {
+    return vec![
+        Arg::with_name("image-fps")
+            .long("image-fps")
+            .takes_value(true)
+            .help("When the input is a directory or a glob of PNG/PPM images, that many images \
+                were taken per second [default: 1]"),
+        Arg::with_name("image-time-from-name")
+            .long("image-time-from-name")
+            .conflicts_with("image-fps")
+            .help("When the input is a directory or a glob of images, take the last number in \
+                each file name as the time of the image in milliseconds"),
+    ];
+}
+
+fn parse_image_timing(matches: &ArgMatches) -> ImageTiming {
+    if matches.is_present("image-time-from-name") {
+        return ImageTiming::FromName;
+    }
+    return match matches.value_of("image-fps") {
+        Some(_) => ImageTiming::FrameRate(parse_value(matches, "image-fps", 1.0)),
+        None => ImageTiming::default(),
+    };
+}
+
+fn pipe_args() -> Vec<Arg<'static, 'static>> {
+    return vec![
+        Arg::with_name("raw-size")
+            .long("raw-size")
+            .takes_value(true)
+            .help("When the input is -, stdin has bare RGB24 frames of this WIDTHxHEIGHT size, \
+                like from ffmpeg -f rawvideo -pix_fmt rgb24, otherwise it has concatenated P6 PPM images"),
+        Arg::with_name("pipe-fps")
+            .long("pipe-fps")
+            .takes_value(true)
+            .help("When the input is -, frame rate of the piped frames [default: 25]"),
+    ];
+}
+
+fn parse_pipe_input(matches: &ArgMatches) -> PipeInput {
+    let defaults = PipeInput::default();
+    let format = match matches.value_of("raw-size") {
+        Some(value) => {
+            let size: Vec<usize> = value.split('x').filter_map(|part| part.trim().parse().ok()).collect();
+            if size.len() != 2 || size[0] == 0 || size[1] == 0 {
+                eprintln!("Invalid value of --raw-size: expected WIDTHxHEIGHT, got {}", value);
+                std::process::exit(1);
+            }
+            PipeFormat::Raw { width: size[0], height: size[1] }
+        },
+        None => defaults.format,
+    };
+    return PipeInput { format, frame_rate: parse_value(matches, "pipe-fps", defaults.frame_rate) };
+}
+
+fn stream_arg() -> Arg<'static, 'static> {
+    return Arg::with_name("stream")
+        .long("stream")
+        .takes_value(true)
+        .help("Index of the video stream in the file, as ffprobe lists them [default: the best video stream]");
+}
+
+fn roi_arg() -> Arg<'static, 'static> {
+    return Arg::with_name("roi")
+        .long("roi")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .help("x,y,width,height of a rectangle where text is expected, like the text panel, \
+            can be repeated, changes and letters outside of these rectangles are ignored");
+}
+
+fn ignore_args() -> Vec<Arg<'static, 'static>> {
+    return vec![
+        Arg::with_name("ignore")
+            .long("ignore")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("x,y,width,height of a rectangle to exclude, like the auto-play indicator, can be repeated"),
+        Arg::with_name("ignore-mask")
+            .long("ignore-mask")
+            .takes_value(true)
+            .help("PPM image of the frame size, pixels that are not black in it are excluded"),
+    ];
+}
+
+fn parse_rects(matches: &ArgMatches, name: &str) -> Vec<Bounds> {
+    return match matches.values_of(name) {
+        Some(values) => values
+            .map(|value| parse_rect(value).unwrap_or_else(|error| {
+                eprintln!("Invalid value of --{}: {}", name, error);
+                std::process::exit(1);
+            }))
+            .collect(),
+        None => Vec::new(),
+    };
+}
+
+fn parse_region(matches: &ArgMatches) -> FrameRegion {
+    let ignore_mask = matches.value_of("ignore-mask").map(|path| {
+        let ppm = fs::read(path)
+            .map_err(Error::io(format!("Failed to read {}", path)))
+            .and_then(decode_ppm);
+        IgnoreMask::from_ppm(&exit_on_error(ppm))
+    });
+    return FrameRegion {
+        rects: parse_rects(matches, "roi"),
+        ignore_rects: parse_rects(matches, "ignore"),
+        ignore_mask,
+    };
+}
+
+fn auto_roi_arg() -> Arg<'static, 'static> {
+    return Arg::with_name("auto-roi")
+        .long("auto-roi")
+        .help("Decode the video one extra time beforehand to find where the text usually \
+            appears and use that as --roi, detected regions are saved in the timeline");
+}
+
+/// when --auto-roi is set, runs the first pass over the video to detect the text region
+fn parse_params_for_video(matches: &ArgMatches, path: &str) -> DetectionParams {
+    let mut params = parse_detection_params(matches);
+    if matches.is_present("auto-roi") {
+        if is_pipe_input(path) {
+            eprintln!("--auto-roi needs to read the input twice, it can not be used with stdin");
+            std::process::exit(1);
+        }
+        params.region = exit_on_error(detect_text_region(path, &params));
+    }
+    return params;
+}
+
+/// the font is identified and sized from the first text frames, so OCR of them waits for that
+fn font_guess_args() -> Vec<Arg<'static, 'static>> {
+    return vec![
+        Arg::with_name("font-candidates")
+            .long("font-candidates")
+            .takes_value(true)
+            .conflicts_with("font-family")
+            .help("Directory of fonts to pick the one of the hardsubs from, instead of --font, letters of the \
+                first text frames are compared to each and the best matching font is used for the rest"),
+        Arg::with_name("font-size")
+            .long("font-size")
+            .takes_value(true)
+            .conflicts_with("font-candidates")
+            .help("Pixel size the hardsubs are rendered at, estimated from the letters of the first text frames \
+                by default"),
+    ];
+}
+
+fn font_args() -> Vec<Arg<'static, 'static>> {
+    return vec![
+        Arg::with_name("font")
+            .long("font")
+            .takes_value(true)
+            .default_value("arial.ttf")
+            .help("TTF/OTF/TTC file of the font used in hardsubs"),
+        Arg::with_name("font-index")
+            .long("font-index")
+            .takes_value(true)
+            .help("Index of the face inside of a TTC/OTC collection given with --font, 0 by default"),
+        Arg::with_name("font-family")
+            .long("font-family")
+            .takes_value(true)
+            .help("Family name of an installed font to use instead of --font, like \"Trebuchet MS\", \
+                or a full face name, like \"Verdana Bold\""),
+        Arg::with_name("font-dir")
+            .long("font-dir")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Directory to look for --font-family in before the system font directories, can be repeated"),
+        Arg::with_name("charset")
+            .long("charset")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Characters to recognize: a preset (basic, letters, digits, punctuation, latin, accented, \
+                latin-accented, hiragana, katakana, kana, japanese) or a Unicode range like U+0410-U+044F, \
+                can be repeated, basic by default, japanese with --japanese"),
+        Arg::with_name("charset-file")
+            .long("charset-file")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("UTF-8 text file with characters to recognize in addition to --charset, like a list of kanji \
+                or the script of the game, can be repeated"),
+        Arg::with_name("extra-chars")
+            .long("extra-chars")
+            .takes_value(true)
+            .help("Characters to recognize in addition to --charset, like \"€£¥\""),
+        Arg::with_name("japanese")
+            .long("japanese")
+            .help("Recognize Japanese: text lines are cut into full-width cells instead of taking every stroke \
+                for a letter, kana and Japanese punctuation are recognized unless --charset is given"),
+        Arg::with_name("ignore-ruby")
+            .long("ignore-ruby")
+            .requires("japanese")
+            .help("Leave out ruby (furigana above kanji) instead of recognizing it as a line of its own"),
+    ];
+}
+
+/// half-width Latin letters are cut two per cell in the Japanese mode, so they are rejected
+/// unless they come from a --charset-file, which can be a whole script with a few English words
+fn check_japanese_charset(japanese: bool, option: &str, charset: &Charset) {
+    let letters = charset.get_half_width_letters();
+    if japanese && !letters.is_empty() {
+        eprintln!(
+            "Invalid {} with --japanese: half-width Latin letters and digits like {:?} are not cut apart in \
+            the Japanese mode, use full-width ones like U+FF10-U+FF5A",
+            option, letters.iter().take(3).collect::<String>()
+        );
+        std::process::exit(1);
+    }
+}
+
+/// union of the --charset presets and ranges, the --charset-file chars and the --extra-chars
+fn parse_charset(matches: &ArgMatches) -> Charset {
+    let japanese = matches.is_present("japanese");
+    let mut charset = match matches.values_of("charset") {
+        Some(specs) => {
+            let mut charset = Charset::new();
+            for spec in specs {
+                let spec_charset = exit_on_error(Charset::parse(spec));
+                check_japanese_charset(japanese, &format!("--charset {}", spec), &spec_charset);
+                charset.extend(&spec_charset);
+            }
+            charset
+        },
+        None if japanese => Charset::preset(JAPANESE_PRESET).unwrap(),
+        None => Charset::default(),
+    };
+    if let Some(paths) = matches.values_of("charset-file") {
+        for path in paths {
+            let mut file_charset = exit_on_error(Charset::from_file(Path::new(path)));
+            if japanese {
+                let removed = file_charset.remove_half_width_letters();
+                if !removed.is_empty() {
+                    eprintln!("Skipping {} half-width Latin letters and digits of {} in the Japanese mode", removed.len(), path);
+                }
+            }
+            charset.extend(&file_charset);
+        }
+    }
+    if let Some(extra_chars) = matches.value_of("extra-chars") {
+        let extra_charset = Charset::from_chars(extra_chars);
+        check_japanese_charset(japanese, "--extra-chars", &extra_charset);
+        charset.extend(&extra_charset);
+    }
+    return charset;
+}
+
+fn parse_segmentation(matches: &ArgMatches) -> Segmentation {
+    if matches.is_present("japanese") {
+        return Segmentation::FullWidthCells { ignore_ruby: matches.is_present("ignore-ruby") };
+    }
+    return Segmentation::Letters;
+}
+
+/// the fonts from --font-candidates if it is set, otherwise the font
+/// from --font-family if that is set, otherwise the --font file,
+/// its size is estimated from the frames unless --font-size is given
+fn load_font(matches: &ArgMatches) -> FontSelection {
+    let charset = parse_charset(matches);
+    let segmentation = parse_segmentation(matches);
+    if let Some(dir) = matches.value_of("font-candidates") {
+        let mut candidates = exit_on_error(load_candidates(Path::new(dir), &charset));
+        for candidate in candidates.iter_mut() {
+            candidate.font_data.segmentation = segmentation;
+        }
+        eprintln!("Identifying the font among {} candidates from {}", candidates.len(), dir);
+        return FontSelection::identify_among(candidates);
+    }
+    let location = get_font_location(matches);
+    let mut font_data = exit_on_error(FontData::from_location(&location, &charset));
+    font_data.segmentation = segmentation;
+    if matches.is_present("font-size") {
+        let size: f32 = parse_value(matches, "font-size", DEFAULT_SCALE);
+        return FontSelection::Known(exit_on_error(font_data.at_scale(size)));
+    }
+    return FontSelection::estimate_size(FontCandidate { location, font_data });
+}
+
+/// the font from --font-family if that is set, otherwise the --font file
+fn get_font_location(matches: &ArgMatches) -> FontLocation {
+    return match matches.value_of("font-family") {
+        Some(family) => {
+            let mut dirs: Vec<PathBuf> = matches.values_of("font-dir")
+                .map(|dirs| dirs.map(PathBuf::from).collect())
+                .unwrap_or_default();
+            dirs.extend(get_system_font_dirs());
+            let location = exit_on_error(find_font(family, &dirs));
+            eprintln!("Using font {} face {}", location.path.display(), location.face_index);
+            location
+        },
+        None => FontLocation {
+            path: PathBuf::from(matches.value_of("font").unwrap()),
+            face_index: parse_value(matches, "font-index", 0),
+        },
+    };
+}
+
+/// --font-size or the default size, nothing is held back to estimate it
+fn load_live_font(matches: &ArgMatches) -> FontData {
+    let mut font_data = exit_on_error(FontData::from_location(&get_font_location(matches), &parse_charset(matches)));
+    font_data.segmentation = parse_segmentation(matches);
+    let size: f32 = parse_value(matches, "font-size", DEFAULT_SCALE);
+    return exit_on_error(font_data.at_scale(size));
+}
+
+/// errors reach the user as a message rather than a panic backtrace
+fn exit_on_error<T>(result: Result<T>) -> T {
+    return result.unwrap_or_else(|error| {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    });
+}
+
+fn parse_value<T: std::str::FromStr>(matches: &ArgMatches, name: &str, default: T) -> T {
+    return match matches.value_of(name) {
+        Some(value) => value.parse().unwrap_or_else(|_| {
+            eprintln!("Invalid value of --{}: {}", name, value);
+            std::process::exit(1);
+        }),
+        None => default,
+    };
+}
+
+fn parse_detection_params(matches: &ArgMatches) -> DetectionParams {
+    let defaults = DetectionParams::default();
+    return DetectionParams {
+        pixel_noise_threshold: parse_value(
+            matches, "pixel-noise-threshold", defaults.pixel_noise_threshold,
+        ),
+        change_factor_threshold: parse_value(
+            matches, "change-factor-threshold", defaults.change_factor_threshold,
+        ),
+        classifier: ClassifierParams {
+            min_text_area: parse_value(matches, "min-text-area", defaults.classifier.min_text_area),
+            max_text_area: parse_value(matches, "max-text-area", defaults.classifier.max_text_area),
+        },
+        region: parse_region(matches),
+        settle_frames: parse_value(matches, "settle-frames", defaults.settle_frames),
+        learn_blinking: matches.is_present("learn-blinking"),
+        threads: parse_value(matches, "threads", defaults.threads),
+        input: InputParams {
+            stream_index: matches.value_of("stream").map(|_| parse_value(matches, "stream", 0)),
+            range: parse_range(matches),
+            image_timing: parse_image_timing(matches),
+            pipe: parse_pipe_input(matches),
+            decode_queue: None,
+        },
+    };
+}
+
+/// parses std::env::args() and runs the subcommand, exits the process on errors
+pub fn run_cli() {
+    let matches = App::new("detect-still-frames")
+        .about("Detects frames in which hardsub text changes and OCR-s them")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("detect")
+                .about("Dumps frames in which hardsub text changes to PPM files")
+                .arg(Arg::with_name("input").long("input").short("i").takes_value(true).required(true)
+                    .help("Video file to process, a directory or a glob like shots/*.png of images, or - for frames from stdin"))
+                .arg(Arg::with_name("output").long("output").short("o").takes_value(true)
+                    .default_value("out/change_frames")
+                    .help("Directory to write frameN_old.ppm and frameN_new.ppm files to"))
+                .arg(Arg::with_name("timeline").long("timeline").takes_value(true)
+                    .help("JSON file to write timestamps of changed frames to [default: <output>/timeline.json]"))
+                .arg(timeline_all_frames_arg())
+                .args(&threshold_args())
+                .arg(threads_arg())
+                .arg(stream_arg())
+                .args(&range_args())
+                .args(&image_args())
+                .args(&pipe_args())
+                .arg(roi_arg())
+                .args(&ignore_args())
+                .arg(auto_roi_arg())
+        )
+        .subcommand(
+            SubCommand::with_name("ocr")
+                .about("Recognizes text in a frame previously dumped by the detect command")
+                .arg(Arg::with_name("input").long("input").short("i").takes_value(true)
+                    .default_value("out/change_frames")
+                    .help("Directory with the dumped frame files"))
+                .arg(Arg::with_name("frame").long("frame").takes_value(true).required(true)
+                    .help("Name of the dumped frame, like frame15"))
+                .arg(Arg::with_name("output").long("output").short("o").takes_value(true)
+                    .help("Text file to write recognized lines to"))
+                .args(&font_args())
+                .args(&font_guess_args())
+                .arg(roi_arg())
+                .args(&ignore_args())
+                .arg(Arg::with_name("timeline").long("timeline").takes_value(true).conflicts_with("roi")
+                    .help("JSON timeline written by the detect command, its text_regions are used as --roi"))
+        )
+        .subcommand(
+            SubCommand::with_name("run")
+                .about("Decodes the video, OCR-s every text change and writes a .srt file")
+                .arg(Arg::with_name("input").long("input").short("i").takes_value(true).required(true)
+                    .help("Video file to process, a directory or a glob like shots/*.png of images, or - for frames from stdin"))
+                .arg(Arg::with_name("output").long("output").short("o").takes_value(true)
+                    .default_value("out/subs.srt")
+                    .help("SubRip file to write"))
+                .arg(Arg::with_name("timeline").long("timeline").takes_value(true)
+                    .help("JSON file to write timestamps of changed frames to"))
+                .arg(timeline_all_frames_arg())
+                .args(&font_args())
+                .args(&font_guess_args())
+                .args(&threshold_args())
+                .arg(threads_arg())
+                .arg(stream_arg())
+                .args(&range_args())
+                .args(&image_args())
+                .args(&pipe_args())
+                .arg(roi_arg())
+                .args(&ignore_args())
+                .arg(auto_roi_arg())
+        )
+        .subcommand(
+            SubCommand::with_name("live")
+                .about("Reads frames as they arrive, like from a capture pipe, and prints recognized text as soon as it settles")
+                .arg(Arg::with_name("input").long("input").short("i").takes_value(true).default_value("-")
+                    .help("- for frames from stdin, or a video file that is being written"))
+                .arg(Arg::with_name("output").long("output").short("o").takes_value(true)
+                    .help("Text file to also append recognized lines to"))
+                .args(&font_args())
+                .arg(Arg::with_name("font-size").long("font-size").takes_value(true)
+                    .help("Pixel size the hardsubs are rendered at, 24 by default, it is not estimated here, \
+                        since text is printed as soon as it settles"))
+                .args(&threshold_args())
+                .args(&pipe_args())
+                .arg(roi_arg())
+                .args(&ignore_args())
+        )
+        .subcommand(
+            SubCommand::with_name("bench")
+                .about("Measures detection speed on a generated clip with one and with many threads")
+                .arg(Arg::with_name("frames").long("frames").takes_value(true).default_value("500")
+                    .help("Number of frames in the clip"))
+                .arg(Arg::with_name("width").long("width").takes_value(true).default_value("1280"))
+                .arg(Arg::with_name("height").long("height").takes_value(true).default_value("720"))
+                .arg(threads_arg())
+        )
+        .get_matches();
+
+    match matches.subcommand() {
+        ("detect", Some(sub)) => {
+            let out_dir = sub.value_of("output").unwrap();
+            let timeline_path = match sub.value_of("timeline") {
+                Some(path) => path.to_string(),
+                None => format!("{}/timeline.json", out_dir),
+            };
+            let path = sub.value_of("input").unwrap();
+            exit_on_error(detect_still_frames(
+                path,
+                out_dir,
+                &timeline_path,
+                sub.is_present("timeline-all-frames"),
+                &parse_params_for_video(sub, path),
+            ));
+        },
+        ("ocr", Some(sub)) => {
+            let region = match sub.value_of("timeline") {
+                Some(timeline_path) => FrameRegion {
+                    rects: exit_on_error(
+                        load_text_regions(timeline_path).map_err(Error::io(format!("Failed to read {}", timeline_path)))
+                    ),
+                    ..parse_region(sub)
+                },
+                None => parse_region(sub),
+            };
+            let lines = exit_on_error(ocr_out_from_image(
+                sub.value_of("input").unwrap(),
+                sub.value_of("frame").unwrap(),
+                &mut load_font(sub),
+                &region,
+            ));
+            if let Some(out_path) = sub.value_of("output") {
+                exit_on_error(
+                    fs::write(out_path, lines.join("\n") + "\n").map_err(Error::io(format!("Failed to write {}", out_path)))
+                );
+            }
+        },
+        ("run", Some(sub)) => {
+            let path = sub.value_of("input").unwrap();
+            exit_on_error(video_to_srt(
+                path,
+                sub.value_of("output").unwrap(),
+                sub.value_of("timeline"),
+                sub.is_present("timeline-all-frames"),
+                &mut load_font(sub),
+                &parse_params_for_video(sub, path),
+            ));
+        },
+        ("live", Some(sub)) => {
+            exit_on_error(live_ocr(
+                sub.value_of("input").unwrap(),
+                sub.value_of("output"),
+                &load_live_font(sub),
+                &parse_detection_params(sub),
+            ));
+        },
+        ("bench", Some(sub)) => {
+            let clip = SyntheticClip {
+                width: parse_value(sub, "width", 1280),
+                height: parse_value(sub, "height", 720),
+                frames: parse_value(sub, "frames", 500),
+            };
+            run_benchmark(&clip, parse_value(sub, "threads", 0));
+        },
+        _ => unreachable!(),
+    }
+}
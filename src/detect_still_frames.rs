@@ -1,29 +1,22 @@
-extern crate ffmpeg_next as ffmpeg;
-
-use ffmpeg::format::{input, Pixel};
-use ffmpeg::media::Type;
-use ffmpeg::software::scaling::{context::Context, flag::Flags};
-use ffmpeg::util::frame::video::Video;
-use std::fs::File;
+use std::cell::Cell;
+use std::fs::{self, File};
 use std::io::prelude::*;
+use std::sync::Arc;
+use crate::error::{Error, Result};
 use crate::ppm_format;
-
-fn make_scaler(decoder: &ffmpeg::decoder::video::Video) -> Result<Context, ffmpeg::Error> {
-    Context::get(
-        decoder.format(),
-        decoder.width(),
-        decoder.height(),
-        Pixel::RGB24,
-        decoder.width(),
-        decoder.height(),
-        Flags::BILINEAR,
-    )
-}
-
-const PIXEL_COLORS: &[&str] = &["RED", "GREEN", "BLUE"];
-
-/// should have taken from ffprobe, but nah...
-const FRAME_RATE: f32 = 24.390244;
+use crate::frame_time::FrameTime;
+use crate::pixel_utils::{Color, Point};
+use crate::rel_matrix::{extend_bounds, Bounds};
+use crate::frame_region::FrameRegion;
+use crate::timeline::Timeline;
+use crate::subtitle_events::{EventEnd, EventSegmenter};
+use crate::blink_mask::BlinkLearner;
+use crate::scene_detection::{find_subtitle_points, get_luma_stats, LumaStats, SceneChange, SceneDetector};
+use crate::change_classifier::{classify_change, ChangeKind, ChangeShape, ClassifierParams};
+use crate::frame_pipeline::process_frames;
+use crate::rgb_frame::RgbFrame;
+use crate::frame_blocks::{get_changed_blocks, BlockGrid, PixelMask};
+use crate::video_decoding::{spawn_decoding, InputParams};
 
 /// summed change of R,G,B values of a pixel to be considered
 const PIXEL_NOISE_THRESHOLD: u32 = 20;
@@ -39,165 +32,771 @@ const PIXEL_NOISE_THRESHOLD: u32 = 20;
 // game text animation frame   : 0.0011677531545020159
 const CHANGE_FACTOR_THRESHOLD: f64 = 0.001;
 
-/// lengthy text is usually about 50k pixels
-const QUALITY_JUMP_POINTS_THRESHOLD: usize = 75000;
+/// tuned on a 1280x720 recording, where that was 100 points: changes of that portion of the
+/// frame or less do not count as a new letter of text animation, some games have a blinking
+/// cursor after the last letter
+const SETTLE_NOISE_AREA: f64 = 0.0001;
+
+/// only white insides of letters are found when looking for text after a scene change, which
+/// is about that portion of points that would change if the same text appeared on its own
+const SUBTITLE_POINTS_PORTION: f64 = 0.25;
+
+/// the heuristic numbers above, overridable from the command line
+#[derive(Debug, Clone)]
+pub struct DetectionParams {
+    pub pixel_noise_threshold: u32,
+    pub change_factor_threshold: f64,
+    /// text change size limits relative to the frame size
+    pub classifier: ClassifierParams,
+    /// changes outside of it are not counted
+    pub region: FrameRegion,
+    /// when set, text change is reported only after text stayed unchanged for that many
+    /// frames, for games that reveal text letter by letter, 0 reports the first frame
+    pub settle_frames: usize,
+    /// mask out small parts of the frame that keep changing periodically, see BlinkLearner
+    pub learn_blinking: bool,
+    /// number of threads comparing frames, 0 means one per CPU core
+    pub threads: usize,
+    /// which frames to take and how to read them
+    pub input: InputParams,
+}
+
+impl Default for DetectionParams {
+    fn default() -> DetectionParams {
+        return DetectionParams {
+            pixel_noise_threshold: PIXEL_NOISE_THRESHOLD,
+            change_factor_threshold: CHANGE_FACTOR_THRESHOLD,
+            classifier: ClassifierParams::default(),
+            region: FrameRegion::whole_frame(),
+            settle_frames: 0,
+            learn_blinking: false,
+            threads: 0,
+            input: InputParams::default(),
+        };
+    }
+}
 
-struct NewFrameInfo {
+pub struct NewFrameInfo {
     change_factor: f64,
     real_points_changed: usize,
+    kind: ChangeKind,
     is_text_change: bool,
+    /// box around all points that changed more than pixel_noise_threshold
+    changed_bounds: Option<Bounds>,
+    /// changed points that are white, like hardsub letters, in the new frame
+    appeared_text_points: usize,
+    /// changed points that were white in the old frame
+    vanished_text_points: usize,
+    /// number of changed points in each BLOCK_SIZE x BLOCK_SIZE block, row by row
+    block_points_changed: Vec<u32>,
     /// meaningful only when is_text_change set
     text_only_frame: Vec<u8>,
 }
 
-/// compares every pixel in both frames and returns a float number in range [0..1]
+fn get_color(pixel_bytes: &[u8], pixel_index: usize) -> Color {
+    let byte_index = pixel_index * 3;
+    return Color {
+        r: pixel_bytes[byte_index + 0],
+        g: pixel_bytes[byte_index + 1],
+        b: pixel_bytes[byte_index + 2],
+    };
+}
+
+/// blocks with changed points relative to all blocks of the frame, like the area, not to
+/// the blocks of the region, otherwise text would cover most of a tight --roi around the panel
+fn get_spread(block_points_changed: &[u32]) -> f64 {
+    let touched_blocks = block_points_changed.iter().filter(|points| **points > 0).count();
+    return if block_points_changed.is_empty() { 0.0 } else { touched_blocks as f64 / block_points_changed.len() as f64 };
+}
+
+/// changed points relative to all points of the region in blocks with changed points
+fn get_density(block_points_changed: &[u32], block_region_pixels: &[u32]) -> f64 {
+    let mut changed: u64 = 0;
+    let mut total: u64 = 0;
+    for (points, pixels) in block_points_changed.iter().zip(block_region_pixels) {
+        if *points > 0 {
+            changed += *points as u64;
+            total += *pixels as u64;
+        }
+    }
+    return if total > 0 { changed as f64 / total as f64 } else { 0.0 };
+}
+
+/// compares every pixel of the region in both frames and returns a float number in range [0..1]
 /// representing how much did the colors change (0 = completely same image,
-/// 1 = completely white image changed to completely black or vice-versa)
-fn analyze_new_frame(old_frame: &Video, new_frame: &Video) -> NewFrameInfo {
-    // TODO: detect that considerable change is only applied to a _part_ of image, to
-    let old_pixel_bytes = old_frame.data(0);
-    let new_pixel_bytes = new_frame.data(0);
-    if old_pixel_bytes.len() != new_pixel_bytes.len() {
-        panic!(
-            "pizdeeeeeeec, two frames have different bitmap sizes: {} and {}",
-            old_pixel_bytes.len(),
-            new_pixel_bytes.len()
-        )
+/// 1 = completely white image changed to completely black or vice-versa),
+/// pixel_mask tells for each pixel whether it is taken into account, only
+/// blocks that differ by checksum are compared pixel by pixel
+fn analyze_new_frame(
+    old_frame: &RgbFrame,
+    new_frame: &RgbFrame,
+    params: &DetectionParams,
+    pixel_mask: &PixelMask,
+) -> Result<NewFrameInfo> {
+    let old_pixel_bytes = &old_frame.bitmap;
+    let new_pixel_bytes = &new_frame.bitmap;
+    if !old_frame.is_same_size(new_frame) || pixel_mask.pixels.len() != new_frame.width * new_frame.height {
+        return Err(Error::FrameSize {
+            expected: (old_frame.width, old_frame.height),
+            got: (new_frame.width, new_frame.height),
+        });
     }
-    let bitmap_size = old_pixel_bytes.len() as usize;
-    let width = old_frame.width();
+    let width = new_frame.width;
+    let height = new_frame.height;
+    let grid = BlockGrid::new(width, height);
+    let changed_blocks = get_changed_blocks(old_frame.get_block_checksums(), new_frame.get_block_checksums());
 
     let mut real_points_changed = 0;
-    let mut text_only_frame = vec![0; bitmap_size];
-    let mut total_change: f64 = 0.0;
-
-    for pixel_index in 0..bitmap_size / 3 {
-        let mut pixel_change: u32 = 0;
-        let x = (pixel_index % width as usize) as u32;
-        let y = (pixel_index / width as usize) as u32;
-        for color_index in 0..PIXEL_COLORS.len() as u8 {
-            let byte_index = pixel_index * 3 + color_index as usize;
-            // does not matter whether it's red, green or blue byte
-            let old_byte = old_pixel_bytes[byte_index];
-            let new_byte = new_pixel_bytes[byte_index];
-            let change_byte = if old_byte > new_byte {
-                old_byte - new_byte
-            } else {
-                new_byte - old_byte
-            };
-            if change_byte > 0 {
-                let color_str = PIXEL_COLORS[color_index as usize];
-                // println!("x: {}, y: {}, color: {}, change: {}", x, y, color_str, change_byte);
-                total_change += change_byte as f64 / 255.0;
-            }
-            pixel_change += change_byte as u32;
+    let mut changed_bounds = None;
+    let mut appeared_text_points = 0;
+    let mut vanished_text_points = 0;
+    let mut text_only_frame = vec![0; new_pixel_bytes.len()];
+    // summed in whole byte values and divided once in the end
+    let mut total_change: u64 = 0;
+    let region_pixels = pixel_mask.region_pixels;
+    let block_region_pixels = &pixel_mask.block_region_pixels;
+    let mut block_points_changed = vec![0; grid.block_count()];
+    let mut changed_points_change: u64 = 0;
+
+    for block_index in 0..grid.block_count() {
+        if !changed_blocks[block_index] || block_region_pixels[block_index] == 0 {
+            continue;
         }
-        if pixel_change >= PIXEL_NOISE_THRESHOLD {
-            for color_index in 0..PIXEL_COLORS.len() as u8 {
-                let byte_index = pixel_index * 3 + color_index as usize;
-                text_only_frame[byte_index] = new_pixel_bytes[byte_index];
+        let (x_range, y_range) = grid.get_block_ranges(block_index);
+        for y in y_range {
+            for x in x_range.clone() {
+                let pixel_index = y * width + x;
+                if !pixel_mask.pixels[pixel_index] {
+                    continue;
+                }
+                let byte_index = pixel_index * 3;
+                let old_rgb = &old_pixel_bytes[byte_index..byte_index + 3];
+                let new_rgb = &new_pixel_bytes[byte_index..byte_index + 3];
+                // does not matter whether it's red, green or blue byte
+                let pixel_change: u32 = old_rgb.iter()
+                    .zip(new_rgb)
+                    .map(|(old_byte, new_byte)| (*old_byte as i32 - *new_byte as i32).abs() as u32)
+                    .sum();
+                total_change += pixel_change as u64;
+                if pixel_change >= params.pixel_noise_threshold {
+                    text_only_frame[byte_index..byte_index + 3].copy_from_slice(new_rgb);
+                    real_points_changed += 1;
+                    changed_points_change += pixel_change as u64;
+                    block_points_changed[block_index] += 1;
+                    changed_bounds = Some(extend_bounds(changed_bounds, Point { x: x as i64, y: y as i64 }));
+                    if get_color(new_pixel_bytes, pixel_index).is_nearly_white() {
+                        appeared_text_points += 1;
+                    }
+                    if get_color(old_pixel_bytes, pixel_index).is_nearly_white() {
+                        vanished_text_points += 1;
+                    }
+                }
             }
-            real_points_changed += 1;
         }
     }
-    let change_factor = total_change / old_pixel_bytes.len() as f64;
+    let change_factor = if region_pixels > 0 {
+        total_change as f64 / 255.0 / (region_pixels * 3) as f64
+    } else {
+        0.0
+    };
 
-    let is_text_change = change_factor > CHANGE_FACTOR_THRESHOLD
-        && real_points_changed < QUALITY_JUMP_POINTS_THRESHOLD
-        && real_points_changed > 15000;
+    let kind = classify_change(&ChangeShape {
+        area: real_points_changed as f64 / (width * height) as f64,
+        spread: get_spread(&block_points_changed),
+        amplitude: if real_points_changed > 0 {
+            changed_points_change as f64 / (real_points_changed * 255 * 3) as f64
+        } else {
+            0.0
+        },
+        density: get_density(&block_points_changed, block_region_pixels),
+    }, &params.classifier);
+    let is_text_change = change_factor > params.change_factor_threshold && kind == ChangeKind::Text;
 
-    return NewFrameInfo {
+    return Ok(NewFrameInfo {
         change_factor,
         real_points_changed,
+        kind,
         is_text_change,
+        changed_bounds,
+        appeared_text_points,
+        vanished_text_points,
+        block_points_changed,
         text_only_frame,
+    });
+}
+
+/// everything about a frame that does not depend on the state kept between
+/// frames, so it can be computed for many frames at once on different threads
+pub struct FrameAnalysis {
+    pub frame: Arc<RgbFrame>,
+    /// none for the first frame or when the frame size changed
+    pub info: Option<NewFrameInfo>,
+    /// none when it is the same as of the last frame
+    pub luma: Option<LumaStats>,
+    /// the mask the frame was analyzed with, it may be outdated by the time the frame is processed
+    pub pixel_mask: Arc<PixelMask>,
+}
+
+/// is_same_mask tells whether the last frame was analyzed with the same pixel mask,
+/// otherwise its luma stats can not be reused even if no pixel changed
+pub fn analyze_frame(
+    last_frame: Option<&RgbFrame>,
+    frame: Arc<RgbFrame>,
+    params: &DetectionParams,
+    pixel_mask: &Arc<PixelMask>,
+    is_same_mask: bool,
+) -> FrameAnalysis {
+    let info = last_frame.and_then(|last_frame| match analyze_new_frame(last_frame, &frame, params, pixel_mask) {
+        Ok(info) => Some(info),
+        Err(error) => {
+            eprintln!("Frame {} at {} ms is not compared to the last one: {}", frame.time.frame_index, frame.time.millis, error);
+            None
+        },
+    });
+    // luma is measured outside of the region too, so no change inside of it is not enough
+    let is_same_luma = is_same_mask && match last_frame {
+        Some(last_frame) => last_frame.is_same_size(&frame)
+            && last_frame.get_block_checksums() == frame.get_block_checksums(),
+        None => false,
     };
+    let luma = if is_same_luma { None } else { Some(get_luma_stats(&frame.bitmap, &pixel_mask.scene_pixels)) };
+    return FrameAnalysis { frame, info, luma, pixel_mask: pixel_mask.clone() };
 }
 
 fn save_file(
     bitmap: &[u8],
     ppm_header: &str,
+    out_dir: &str,
     name: String,
-) -> std::result::Result<(), std::io::Error> {
-    let mut file = File::create(format!("out/change_frames/{}.ppm", name))?;
-    file.write_all(ppm_header.as_bytes())?;
-    file.write_all(bitmap)?;
+) -> Result<()> {
+    let path = format!("{}/{}.ppm", out_dir, name);
+    let mut file = File::create(&path).map_err(Error::io(format!("Failed to create {}", path)))?;
+    file.write_all(ppm_header.as_bytes())
+        .and_then(|_| file.write_all(bitmap))
+        .map_err(Error::io(format!("Failed to write {}", path)))?;
     Ok(())
 }
 
-/// iterate through frames of a video file and, using few heuristic numbers,
-/// detect frames in which hardsub text changes and dump these frames to file
-pub fn detect_still_frames() -> Result<(), ffmpeg::Error> {
-    ffmpeg::init().unwrap();
-
-    let path = "assets/fmd_muramasa_maachan_05.webm";
-
-    if let Ok(mut ictx) = input(&path) {
-        let input = ictx
-            .streams()
-            .best(Type::Video)
-            .ok_or(ffmpeg::Error::StreamNotFound)?;
-        let video_stream_index = input.index();
-
-        let mut decoder = input.codec().decoder().video()?;
-        let mut scaler = make_scaler(&decoder)?;
-
-        let mut frame_index = 0;
-        let mut last_frame = Video::empty();
-
-        let mut receive_and_process_decoded_frames =
-            |decoder: &mut ffmpeg::decoder::Video| -> Result<(), ffmpeg::Error> {
-                let mut decoded = Video::empty();
-                while decoder.receive_frame(&mut decoded).is_ok() {
-                    let mut rgb_frame = Video::empty();
-
-                    scaler.run(&decoded, &mut rgb_frame)?;
-                    let ppm_header = ppm_format::make_header(
-                        rgb_frame.width() as usize,
-                        rgb_frame.height() as usize
-                    );
-
-                    if frame_index > 0 {
-                        let seconds = (frame_index as f32 / FRAME_RATE).floor();
-                        let rel_frame_index = (frame_index as f32 % FRAME_RATE).round();
-                        let info = analyze_new_frame(&last_frame, &rgb_frame);
-                        if info.is_text_change {
-                            println!(
-                                "Frame {} at {}:{} s. change factor: {}, points: {}",
-                                frame_index,
-                                seconds,
-                                rel_frame_index,
-                                info.change_factor,
-                                info.real_points_changed
-                            );
-                            save_file(
-                                rgb_frame.data(0),
-                                &ppm_header,
-                                format!("frame{}_old", frame_index),
-                            ).unwrap();
-
-                            save_file(
-                                &info.text_only_frame,
-                                &ppm_header,
-                                format!("frame{}_new", frame_index),
-                            ).unwrap();
-                        }
-                    }
-                    frame_index += 1;
-                    last_frame = rgb_frame;
+/// a frame that differs from the previous one, if hardsub text changed in it, the
+/// frame is kept in memory so that it could be either dumped to a file or passed
+/// straight to the OCR
+pub struct FrameChange {
+    pub time: FrameTime,
+    pub width: usize,
+    pub height: usize,
+    pub change_factor: f64,
+    pub real_points_changed: usize,
+    pub kind: ChangeKind,
+    pub is_text_change: bool,
+    pub changed_bounds: Option<Bounds>,
+    pub appeared_text_points: usize,
+    pub vanished_text_points: usize,
+    /// when waiting for text animation to finish, the frame where first letters appeared
+    pub animation_start: Option<FrameTime>,
+    /// when waiting for text animation to finish, the frame where last letters appeared
+    pub animation_end: Option<FrameTime>,
+    /// set on the frame where a cut happened or a fade finished
+    pub scene_change: Option<SceneChange>,
+    /// text found in the frame alone once the scene got stable after a cut or a fade,
+    /// not by comparing to the previous frame, it may be the text that was already there
+    pub is_rescan: bool,
+    /// meaningful only when is_text_change set
+    pub full_bitmap: Vec<u8>,
+    /// meaningful only when is_text_change set
+    pub text_only_bitmap: Vec<u8>,
+}
+
+fn make_frame_change(info: NewFrameInfo, frame: &RgbFrame) -> FrameChange {
+    return FrameChange {
+        time: frame.time,
+        width: frame.width,
+        height: frame.height,
+        change_factor: info.change_factor,
+        real_points_changed: info.real_points_changed,
+        kind: info.kind,
+        is_text_change: info.is_text_change,
+        changed_bounds: info.changed_bounds,
+        appeared_text_points: info.appeared_text_points,
+        vanished_text_points: info.vanished_text_points,
+        animation_start: None,
+        animation_end: None,
+        scene_change: None,
+        is_rescan: false,
+        full_bitmap: if info.is_text_change { frame.bitmap.clone() } else { Vec::new() },
+        text_only_bitmap: info.text_only_frame,
+    };
+}
+
+/// text that started appearing letter by letter and did not stop yet
+struct SettlingText {
+    /// the frame right before the first letters appeared
+    base_frame: Arc<RgbFrame>,
+    started: FrameTime,
+    completed: FrameTime,
+    stable_frames: usize,
+}
+
+/// gets analysis of each next frame in order, all state that has
+/// to be kept between frames of the video lives here
+pub struct ChangeTracker<'a> {
+    params: &'a DetectionParams,
+    last_frame: Option<Arc<RgbFrame>>,
+    settling: Option<SettlingText>,
+    /// change of the last frame while text is settling, reported with the next frame, so
+    /// that if the input ends there it is merged into the settled change of the same frame
+    held_change: Option<FrameChange>,
+    /// the frame before the first of the frames with letter-sized changes in a row and the
+    /// time of that first one, text animation starts there, its first letters are too small
+    /// to be a text change yet
+    letters_start: Option<(Arc<RgbFrame>, FrameTime)>,
+    scene_detector: SceneDetector,
+    blink_learner: Option<BlinkLearner>,
+    /// params.region with learned blinking blocks taken out, for each pixel of the frame,
+    /// shared with analysis of frames that are being compared at the moment
+    pub pixel_mask: Arc<PixelMask>,
+}
+
+impl ChangeTracker<'_> {
+    pub fn new(params: &DetectionParams) -> ChangeTracker<'_> {
+        return ChangeTracker {
+            params,
+            last_frame: None,
+            settling: None,
+            held_change: None,
+            letters_start: None,
+            scene_detector: SceneDetector::new(),
+            blink_learner: None,
+            pixel_mask: Arc::new(PixelMask::new(Vec::new(), Vec::new(), 0, 0)),
+        };
+    }
+
+    fn rebuild_pixel_mask(&mut self, width: usize, height: usize) {
+        let mut pixel_mask = self.params.region.make_pixel_mask(width, height);
+        let mut scene_pixel_mask = self.params.region.make_scene_pixel_mask(width, height);
+        if let Some(learner) = &self.blink_learner {
+            for (pixel_index, (is_considered, is_scene_considered)) in pixel_mask.iter_mut()
+                .zip(scene_pixel_mask.iter_mut())
+                .enumerate() {
+                let point = Point { x: (pixel_index % width) as i64, y: (pixel_index / width) as i64 };
+                if learner.is_masked(&point) {
+                    *is_considered = false;
+                    *is_scene_considered = false;
                 }
-                Ok(())
-            };
+            }
+        }
+        self.pixel_mask = Arc::new(PixelMask::new(pixel_mask, scene_pixel_mask, width, height));
+    }
 
-        for (stream, packet) in ictx.packets() {
-            if stream.index() == video_stream_index {
-                decoder.send_packet(&packet)?;
-                receive_and_process_decoded_frames(&mut decoder)?;
+    /// the mask and learned blinks are made for the size of the first frame,
+    /// has to be called before the frame is passed to the analysis
+    pub fn ensure_frame_size(&mut self, frame: &RgbFrame) {
+        if self.pixel_mask.pixels.len() != frame.width * frame.height {
+            if self.params.learn_blinking {
+                self.blink_learner = Some(BlinkLearner::new(frame.width, frame.height));
             }
+            self.rebuild_pixel_mask(frame.width, frame.height);
+        }
+    }
+
+    fn learn_blinking(&mut self, info: &NewFrameInfo, frame: &RgbFrame) {
+        let got_masked = match &mut self.blink_learner {
+            Some(learner) => learner.learn(frame.time.frame_index, &info.block_points_changed),
+            None => false,
+        };
+        if got_masked {
+            eprintln!("Frame {} at {} ms: masked a periodically blinking block", frame.time.frame_index, frame.time.millis);
+            self.rebuild_pixel_mask(frame.width, frame.height);
         }
-        decoder.send_eof()?;
-        receive_and_process_decoded_frames(&mut decoder)?;
     }
 
+    pub fn get_blinking_regions(&self) -> Vec<Bounds> {
+        return match &self.blink_learner {
+            Some(learner) => learner.get_masked_blocks(),
+            None => Vec::new(),
+        };
+    }
+
+    /// the whole text compared to the frame before animation started
+    fn make_settled_change(&self, settling: &SettlingText, frame: &RgbFrame) -> Option<FrameChange> {
+        let info = match analyze_new_frame(&settling.base_frame, frame, self.params, &self.pixel_mask) {
+            Ok(info) => info,
+            Err(error) => {
+                eprintln!("Dropping text animation since frame {}: {}", settling.started.frame_index, error);
+                return None;
+            },
+        };
+        let mut change = make_frame_change(info, frame);
+        change.animation_start = Some(settling.started);
+        change.animation_end = Some(settling.completed);
+        return Some(change);
+    }
+
+    pub fn process(&mut self, analysis: FrameAnalysis, on_change: &mut impl FnMut(FrameChange)) {
+        if let Some(held_change) = self.held_change.take() {
+            on_change(held_change);
+        }
+        let analysis = if Arc::ptr_eq(&analysis.pixel_mask, &self.pixel_mask) {
+            analysis
+        } else {
+            // frames already handed to the pool when a blink got masked are compared again,
+            // so that the result does not depend on how many of them were in flight
+            let last_frame = self.last_frame.as_deref().filter(|last_frame| last_frame.is_same_size(&analysis.frame));
+            analyze_frame(last_frame, analysis.frame, self.params, &self.pixel_mask, false)
+        };
+        let frame = analysis.frame;
+        let time = frame.time;
+        let (mut info, last_frame) = match (analysis.info, self.last_frame.replace(frame.clone())) {
+            (Some(info), Some(last_frame)) => (info, last_frame),
+            _ => {
+                // the animation can not be compared to a frame of a different size
+                self.settling = None;
+                self.letters_start = None;
+                self.scene_detector.feed(time, analysis.luma);
+                return;
+            },
+        };
+        self.learn_blinking(&info, &frame);
+        let scene_change = self.scene_detector.feed(time, analysis.luma);
+        if scene_change.is_some() {
+            // text that came together with the new scene is looked for once it is stable
+            info.is_text_change = false;
+        }
+        let is_stable = info.kind == ChangeKind::Minor;
+        let is_letter_change = info.real_points_changed as f64 > SETTLE_NOISE_AREA * (frame.width * frame.height) as f64;
+        if !is_letter_change {
+            self.letters_start = None;
+        } else if self.letters_start.is_none() {
+            self.letters_start = Some((last_frame.clone(), time));
+        }
+
+        let mut settled_change = None;
+        if let Some(mut settling) = self.settling.take() {
+            if is_letter_change {
+                settling.stable_frames = 0;
+                settling.completed = time;
+            } else {
+                settling.stable_frames += 1;
+            }
+            // letters appearing one by one are reported in the settled frame, not separately
+            info.is_text_change = false;
+            if settling.stable_frames >= self.params.settle_frames {
+                settled_change = self.make_settled_change(&settling, &frame);
+            } else {
+                self.settling = Some(settling);
+            }
+        } else if info.is_text_change && self.params.settle_frames > 0 {
+            info.is_text_change = false;
+            let (base_frame, started) = self.letters_start.clone().unwrap_or((last_frame, time));
+            self.settling = Some(SettlingText {
+                base_frame,
+                started,
+                completed: time,
+                stable_frames: 0,
+            });
+        }
+
+        if let Some(mut settled_change) = settled_change {
+            // it stands for this frame too, so the frame is not reported twice
+            settled_change.scene_change = scene_change;
+            on_change(settled_change);
+        } else if info.real_points_changed > 0 || scene_change.is_some() {
+            let mut change = make_frame_change(info, &frame);
+            change.scene_change = scene_change;
+            if self.settling.is_some() {
+                self.held_change = Some(change);
+            } else {
+                on_change(change);
+            }
+        }
+        if self.scene_detector.is_settled_after_change(is_stable) {
+            if let Some(text_change) = self.find_text_after_scene_change(&frame) {
+                if let Some(held_change) = self.held_change.take() {
+                    on_change(held_change);
+                }
+                on_change(text_change);
+            }
+        }
+    }
+
+    /// there is no frame without text in the new scene to compare to, so the
+    /// frame itself is checked for points looking like letters with an outline
+    fn find_text_after_scene_change(&self, frame: &RgbFrame) -> Option<FrameChange> {
+        let width = frame.width;
+        let height = frame.height;
+        let found = find_subtitle_points(&frame.bitmap, width, height, &self.pixel_mask.pixels);
+        let min_points = self.params.classifier.min_text_area * (width * height) as f64 * SUBTITLE_POINTS_PORTION;
+        if (found.count as f64) < min_points {
+            return None;
+        }
+        return Some(FrameChange {
+            time: frame.time,
+            width,
+            height,
+            change_factor: 0.0,
+            real_points_changed: found.count,
+            kind: ChangeKind::Text,
+            is_text_change: true,
+            changed_bounds: found.bounds,
+            appeared_text_points: found.count,
+            vanished_text_points: 0,
+            animation_start: None,
+            animation_end: None,
+            scene_change: None,
+            is_rescan: true,
+            full_bitmap: frame.bitmap.clone(),
+            text_only_bitmap: found.text_only_bitmap,
+        });
+    }
+
+    /// video ended while the text was still animating, take whatever it managed to type
+    pub fn finish(&mut self, on_change: &mut impl FnMut(FrameChange)) {
+        let held_change = self.held_change.take();
+        let settled_change = match (self.settling.take(), &self.last_frame) {
+            (Some(settling), Some(last_frame)) => self.make_settled_change(&settling, last_frame),
+            _ => None,
+        };
+        match (settled_change, held_change) {
+            // changes are held only while text settles, so both are of the last frame
+            (Some(mut settled_change), Some(held_change)) => {
+                settled_change.scene_change = held_change.scene_change;
+                on_change(settled_change);
+            },
+            (settled_change, held_change) => {
+                held_change.into_iter().chain(settled_change).for_each(|change| on_change(change));
+            },
+        }
+    }
+}
+
+/// what is known about the video only after all frames are processed
+pub struct DetectionSummary {
+    /// the first processed frame, it is not the first frame of the video when range start is set
+    pub start_time: Option<FrameTime>,
+    /// the moment right after the last frame stops being displayed
+    pub end_time: FrameTime,
+    /// blocks that were excluded from the detection as periodically blinking
+    pub blinking_regions: Vec<Bounds>,
+    /// processing stopped at the end of the range rather than at the end of the video
+    pub is_range_end: bool,
+}
+
+impl DetectionSummary {
+    /// why the text that was still displayed after the last frame ended
+    pub fn get_end_reason(&self) -> EventEnd {
+        return if self.is_range_end { EventEnd::RangeEnd } else { EventEnd::VideoEnd };
+    }
+}
+
+/// iterate through frames of a video file or an image sequence and, using few heuristic numbers, detect frames
+/// in which something changed, in particular hardsub text, frames that are identical to
+/// the previous one are skipped, the video is decoded on a separate thread, the first
+/// error returned by on_change stops the decoding and is returned
+pub fn for_each_frame_change(
+    path: &str,
+    params: &DetectionParams,
+    mut on_change: impl FnMut(FrameChange) -> Result<()>,
+) -> Result<DetectionSummary> {
+    let (frames, decoding) = spawn_decoding(path, &params.input);
+    let mut start_time = None;
+    let is_failed = Cell::new(false);
+    let mut failure = None;
+    let frames = frames.into_iter()
+        .take_while(|_| !is_failed.get())
+        .inspect(|frame| {
+            start_time.get_or_insert(frame.time);
+        });
+    let blinking_regions = process_frames(frames, params, &mut |change| {
+        if failure.is_none() {
+            if let Err(error) = on_change(change) {
+                failure = Some(error);
+                is_failed.set(true);
+            }
+        }
+    });
+    let end_time = decoding.join().expect("decoding thread panicked")?;
+    if let Some(error) = failure {
+        return Err(error);
+    }
+
+    Ok(DetectionSummary {
+        start_time,
+        end_time,
+        blinking_regions,
+        // the last frame lasts till the range end, a frame past it was decoded and dropped
+        is_range_end: params.input.range.is_after_end(&end_time),
+    })
+}
+
+/// dump every frame in which hardsub text changes to a file, full frame and the
+/// text-only frame, so that it could be OCR-ed later, and write a json timeline
+/// with frames that changed noticeably, or with all that differ from the previous one
+pub fn detect_still_frames(
+    path: &str,
+    out_dir: &str,
+    timeline_path: &str,
+    timeline_all_frames: bool,
+    params: &DetectionParams,
+) -> Result<()> {
+    fs::create_dir_all(out_dir).map_err(Error::io(format!("Failed to create {}", out_dir)))?;
+    let mut timeline = Timeline::new(path, timeline_all_frames);
+    timeline.text_regions = params.region.rects.clone();
+    let mut segmenter: EventSegmenter<()> = EventSegmenter::new();
+    let summary = for_each_frame_change(path, params, |change| {
+        timeline.add_frame(&change);
+        if let Some(event) = segmenter.feed(&change, |_| Some(())) {
+            timeline.add_event(&event);
+        }
+        if let Some(scene_change) = &change.scene_change {
+            println!(
+                "Frame {} at {} ms: scene {:?} since frame {} at {} ms",
+                change.time.frame_index, change.time.millis, scene_change.kind,
+                scene_change.start.frame_index, scene_change.start.millis
+            );
+        }
+        if !change.is_text_change {
+            return Ok(());
+        }
+        println!(
+            "Frame {} at {} ms. change factor: {}, points: {}",
+            change.time.frame_index,
+            change.time.millis,
+            change.change_factor,
+            change.real_points_changed
+        );
+        if let (Some(start), Some(end)) = (change.animation_start, change.animation_end) {
+            println!(
+                "  text animation from frame {} at {} ms till frame {} at {} ms",
+                start.frame_index, start.millis, end.frame_index, end.millis
+            );
+        }
+        let ppm_header = ppm_format::make_header(change.width, change.height);
+        save_file(
+            &change.full_bitmap,
+            &ppm_header,
+            out_dir,
+            format!("frame{}_old", change.time.frame_index),
+        )?;
+
+        save_file(
+            &change.text_only_bitmap,
+            &ppm_header,
+            out_dir,
+            format!("frame{}_new", change.time.frame_index),
+        )
+    })?;
+    if let Some(event) = segmenter.finish(summary.end_time, summary.get_end_reason()) {
+        timeline.add_event(&event);
+    }
+    timeline.finish(&summary);
+    timeline.save(timeline_path).map_err(Error::io(format!("Failed to write {}", timeline_path)))?;
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 320;
+    const HEIGHT: usize = 180;
+
+    /// vertical strokes in the bottom panel from x = 20 till text_end_x, about like a line
+    /// of letters typed so far, a stroke is 2px wide and repeats every stroke_period pixels
+    fn make_typed_frame(frame_index: usize, stroke_period: usize, text_end_x: usize) -> RgbFrame {
+        let mut bitmap = vec![100; WIDTH * HEIGHT * 3];
+        for y in 130..170 {
+            for x in (20..text_end_x).filter(|x| x % stroke_period < 2) {
+                let byte_index = (y * WIDTH + x) * 3;
+                bitmap[byte_index..byte_index + 3].copy_from_slice(&[255, 255, 255]);
+            }
+        }
+        return RgbFrame::new(FrameTime { frame_index, pts: None, millis: frame_index as u64 * 40 }, WIDTH, HEIGHT, bitmap);
+    }
+
+    /// strokes across the whole panel
+    fn make_striped_frame(frame_index: usize, stroke_period: Option<usize>) -> RgbFrame {
+        return match stroke_period {
+            Some(stroke_period) => make_typed_frame(frame_index, stroke_period, 300),
+            None => make_typed_frame(frame_index, 6, 20),
+        };
+    }
+
+    fn make_frame(frame_index: usize, with_text: bool) -> RgbFrame {
+        return make_striped_frame(frame_index, if with_text { Some(6) } else { None });
+    }
+
+    fn analyze_with_region(region: FrameRegion) -> NewFrameInfo {
+        let params = DetectionParams { region, ..DetectionParams::default() };
+        let pixel_mask = PixelMask::new(
+            params.region.make_pixel_mask(WIDTH, HEIGHT),
+            params.region.make_scene_pixel_mask(WIDTH, HEIGHT),
+            WIDTH,
+            HEIGHT,
+        );
+        return analyze_new_frame(&make_frame(0, false), &make_frame(1, true), &params, &pixel_mask).unwrap();
+    }
+
+    #[test]
+    fn text_is_text_in_whole_frame() {
+        let info = analyze_with_region(FrameRegion::whole_frame());
+        assert_eq!(info.kind, ChangeKind::Text);
+        assert!(info.is_text_change);
+    }
+
+    #[test]
+    fn text_filling_panel_sized_roi_is_still_text() {
+        let panel = Bounds { start: Point { x: 0, y: 120 }, end: Point { x: WIDTH as i64 - 1, y: HEIGHT as i64 - 1 } };
+        let info = analyze_with_region(FrameRegion { rects: vec![panel], ..FrameRegion::whole_frame() });
+        assert_eq!(info.kind, ChangeKind::Text);
+        assert!(info.is_text_change);
+    }
+
+    #[test]
+    fn bold_text_filling_tight_roi_is_not_a_scene_cut() {
+        // half of the region gets white, which would be a cut if only the region was measured
+        let text_bounds = Bounds { start: Point { x: 20, y: 130 }, end: Point { x: 159, y: 169 } };
+        let params = DetectionParams {
+            region: FrameRegion { rects: vec![text_bounds], ..FrameRegion::whole_frame() },
+            ..DetectionParams::default()
+        };
+        let pixel_mask = Arc::new(PixelMask::new(
+            params.region.make_pixel_mask(WIDTH, HEIGHT),
+            params.region.make_scene_pixel_mask(WIDTH, HEIGHT),
+            WIDTH,
+            HEIGHT,
+        ));
+        let empty_frame = Arc::new(make_striped_frame(0, None));
+        let text_frame = Arc::new(make_striped_frame(1, Some(4)));
+        let empty = analyze_frame(None, empty_frame.clone(), &params, &pixel_mask, true);
+        let text = analyze_frame(Some(&empty_frame), text_frame.clone(), &params, &pixel_mask, true);
+        assert!(text.info.unwrap().is_text_change);
+
+        let mut scene_detector = SceneDetector::new();
+        assert!(scene_detector.feed(empty_frame.time, empty.luma).is_none());
+        assert!(scene_detector.feed(text_frame.time, text.luma).is_none());
+    }
+
+    #[test]
+    fn animation_starts_with_the_first_letter_too_small_for_a_text_change() {
+        let params = DetectionParams { settle_frames: 2, ..DetectionParams::default() };
+        let mut tracker = ChangeTracker::new(&params);
+        let mut changes = Vec::new();
+        let mut last_frame: Option<Arc<RgbFrame>> = None;
+        // a letter of two strokes, then the rest of the line in two steps, then it stays
+        for (frame_index, text_end_x) in [20, 32, 120, 200, 200, 200, 200, 200].iter().enumerate() {
+            let frame = Arc::new(make_typed_frame(frame_index, 6, *text_end_x));
+            tracker.ensure_frame_size(&frame);
+            let pixel_mask = tracker.pixel_mask.clone();
+            let analysis = analyze_frame(last_frame.as_deref(), frame.clone(), &params, &pixel_mask, true);
+            tracker.process(analysis, &mut |change| changes.push(change));
+            last_frame = Some(frame);
+        }
+        tracker.finish(&mut |change| changes.push(change));
+
+        let text_changes: Vec<&FrameChange> = changes.iter().filter(|change| change.is_text_change).collect();
+        assert_eq!(text_changes.len(), 1);
+        assert_eq!(text_changes[0].time.frame_index, 5);
+        assert_eq!(text_changes[0].animation_start.unwrap().frame_index, 1);
+        assert_eq!(text_changes[0].animation_end.unwrap().frame_index, 3);
+    }
+}
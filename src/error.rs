@@ -0,0 +1,63 @@
+//! one error type for everything that can fail on the way from a video file to
+//! the recognized text, so that callers can decide whether to skip or to abort
+
+extern crate ffmpeg_next as ffmpeg;
+
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+    /// ffmpeg failed to open, demux, decode or scale the video
+    Decode(ffmpeg::Error),
+    /// malformed PPM or PNG data, the text tells what is wrong and where
+    Image(String),
+    /// font file can not be parsed or lacks a glyph needed for the OCR
+    Font(String),
+    /// file or pipe can not be read or written
+    Io { context: String, source: io::Error },
+    /// frame of a different size got to a comparison, like after a resolution change mid-stream
+    FrameSize { expected: (usize, usize), got: (usize, usize) },
+    /// the input does not have what was asked for, like no images in the directory
+    Input(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// for map_err, like `fs::read(path).map_err(Error::io(format!("Failed to read {}", path)))`
+    pub fn io(context: String) -> impl FnOnce(io::Error) -> Error {
+        return move |source| Error::Io { context, source };
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Error::Decode(error) => write!(f, "Failed to decode the video: {}", error),
+            Error::Image(message) => write!(f, "{}", message),
+            Error::Font(message) => write!(f, "{}", message),
+            Error::Io { context, source } => write!(f, "{}: {}", context, source),
+            Error::FrameSize { expected, got } => write!(
+                f, "Frame is {}x{} while {}x{} was expected", got.0, got.1, expected.0, expected.1
+            ),
+            Error::Input(message) => write!(f, "{}", message),
+        };
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return match self {
+            Error::Decode(error) => Some(error),
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        };
+    }
+}
+
+impl From<ffmpeg::Error> for Error {
+    fn from(error: ffmpeg::Error) -> Error {
+        return Error::Decode(error);
+    }
+}
@@ -1,52 +1,194 @@
 use ab_glyph::{point, Point, Glyph, Font, FontRef};
 use std::collections::HashMap;
-use crate::rel_matrix::{RelMatrix, PixelCoverage, make_rel_bitmap};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use crate::charset::Charset;
+use crate::cjk_segmentation::Segmentation;
+use crate::error::{Error, Result};
+use crate::font_search::FontLocation;
+use crate::rel_matrix::{RelMatrix, PixelCoverage, Signature, make_rel_bitmap};
 
-pub const CHAR_OPTIONS: [char; 54] = [
-    'q', 'w', 'e', 'r', 't', 'y', 'u', 'i', 'o', 'p', 'a', 's', 'd', 'f', 'g', 'h', 'j', 'k',
-    'l', 'z', 'x', 'c', 'v', 'b', 'n', 'm', 'Q', 'W', 'E', 'R', 'T', 'Y', 'U', 'I', 'O', 'P',
-    'A', 'S', 'D', 'F', 'G', 'H', 'J', 'K', 'L', 'Z', 'X', 'C', 'V', 'B', 'N', 'M', ',', '.',
-];
+/// pixel size the fonts are rendered at till the size of the hardsubs is known
+pub const DEFAULT_SCALE: f32 = 24.0;
 
-fn get_font_bitmap(char: char, shift: &ab_glyph::Point, font: &FontRef) -> RelMatrix {
+/// an estimated size can be a pixel or so off, so each char is also rendered
+/// a bit smaller and a bit bigger, the variant that fits best wins during the OCR
+const SCALE_SPREAD: [f32; 3] = [0.96, 1.0, 1.04];
+
+/// charsets bigger than that, like kana with kanji, are rendered without sub-pixel shifts, since
+/// big glyphs hardly need them, and are narrowed down by Signature before the comparison
+pub const LARGE_CHARSET: usize = 200;
+
+/// proportions of the font per pixel of scale, compared to the letters of
+/// a frame to tell what size the hardsubs were rendered at
+#[derive(Debug, Clone, Copy)]
+pub struct FontMetrics {
+    /// height of the lowercase "x", None if the font has no such glyph
+    pub x_height: Option<f32>,
+    /// height of the capital "H", None if the font has no such glyph
+    pub cap_height: Option<f32>,
+    /// distance between baselines of two lines
+    pub line_pitch: f32,
+    /// height of "国", the box kanji fill, None if the font has no such glyph
+    pub ideograph_height: Option<f32>,
+    /// width of a full-width char
+    pub em: f32,
+}
+
+impl FontMetrics {
+    fn measure(font: &FontRef) -> FontMetrics {
+        let height = font.height_unscaled();
+        // glyph 0 is the box drawn for missing chars
+        let get_char_height = |char: char| Some(font.glyph_id(char))
+            .filter(|glyph_id| glyph_id.0 != 0)
+            .and_then(|glyph_id| font.outline(glyph_id))
+            .map(|outline| outline.bounds.height().abs() / height);
+        return FontMetrics {
+            x_height: get_char_height('x'),
+            cap_height: get_char_height('H'),
+            line_pitch: (height + font.line_gap_unscaled()) / height,
+            ideograph_height: get_char_height('国'),
+            em: font.units_per_em().unwrap_or(height) / height,
+        };
+    }
+}
+
+/// None if the font has no outline for the char, like when it lacks the glyph
+fn get_font_bitmap(char: char, scale: f32, shift: &ab_glyph::Point, font: &FontRef) -> Option<RelMatrix> {
     let glyph: Glyph = font
         .glyph_id(char)
-        .with_scale_and_position(24.0, *shift);
+        .with_scale_and_position(scale, *shift);
 
-    let outlined = font.outline_glyph(glyph).unwrap();
+    let outlined = font.outline_glyph(glyph)?;
 
     let mut coverages = Vec::new();
     outlined.draw(|x, y, c| {
         coverages.push(PixelCoverage { x, y, c });
     });
-    return make_rel_bitmap(coverages);
+    return Some(make_rel_bitmap(coverages));
+}
+
+fn parse_font(font_bytes: &[u8], face_index: u32) -> Result<FontRef<'_>> {
+    return FontRef::try_from_slice_and_index(font_bytes, face_index)
+        .map_err(|error| Error::Font(format!("Failed to parse font face {}: {}", face_index, error)));
 }
 
 pub struct FontData {
-    pub char_to_shift_to_matrix: HashMap<char, Vec<RelMatrix>>,
+    /// every shift of every size of the spread, see get_bitmaps()
+    pub(crate) char_to_shift_to_matrix: HashMap<char, Vec<RelMatrix>>,
+    /// chars of the charset the font has glyphs for, in the order of the charset
+    pub chars: Vec<char>,
+    /// rough shape of each char at the middle of the spread
+    pub(crate) char_to_signature: HashMap<char, Signature>,
+    /// pixel size the chars are rendered at, the middle of the spread
+    pub scale: f32,
+    pub(crate) metrics: FontMetrics,
+    /// how the letters to compare to this font are cut out of frames, kept by at_scale()
+    pub segmentation: Segmentation,
+    font_bytes: Arc<Vec<u8>>,
+    face_index: u32,
 }
 
 impl FontData {
-    pub fn init(font: &FontRef) -> FontData {
+    /// chars the font has no glyph for are left out
+    fn init(font_bytes: Arc<Vec<u8>>, face_index: u32, scale: f32, charset: &[char]) -> Result<FontData> {
+        let font = parse_font(&font_bytes, face_index)?;
         let shift_options = [
             point(0.0, 0.0),
             point(0.5, 0.0),
             point(0.0, 0.5),
             point(0.5, 0.5),
         ];
+        let shift_options = if charset.len() > LARGE_CHARSET { &shift_options[..1] } else { &shift_options[..] };
         let mut char_to_shift_to_matrix: HashMap<char, Vec<RelMatrix>> = HashMap::new();
-        for char in &CHAR_OPTIONS {
-            let bitmaps: Vec<RelMatrix> = shift_options.iter()
-                .map(|font_shift| get_font_bitmap(*char, font_shift, font) )
+        let mut char_to_signature: HashMap<char, Signature> = HashMap::new();
+        let mut chars = Vec::new();
+        for char in charset {
+            if font.glyph_id(*char).0 == 0 {
+                continue;
+            }
+            let bitmaps: Option<Vec<RelMatrix>> = SCALE_SPREAD.iter()
+                .flat_map(|portion| shift_options.iter().map(move |font_shift| (scale * portion, font_shift)))
+                .map(|(char_scale, font_shift)| get_font_bitmap(*char, char_scale, font_shift, &font))
                 .collect();
-            char_to_shift_to_matrix.insert(*char, bitmaps);
+            if let Some(bitmaps) = bitmaps {
+                let middle = SCALE_SPREAD.len() / 2 * shift_options.len();
+                char_to_signature.insert(*char, Signature::new(&bitmaps[middle].bitmap));
+                char_to_shift_to_matrix.insert(*char, bitmaps);
+                chars.push(*char);
+            }
         }
-        return FontData {
+        if chars.is_empty() {
+            return Err(Error::Font("Font has none of the chars of the charset".to_string()));
+        }
+        let metrics = FontMetrics::measure(&font);
+        return Ok(FontData {
             char_to_shift_to_matrix,
+            chars,
+            char_to_signature,
+            scale,
+            metrics,
+            segmentation: Segmentation::default(),
+            font_bytes: font_bytes.clone(),
+            face_index,
+        });
+    }
+
+    /// renders the chars of the default charset in a TTF/OTF font file, or in the first face of a TTC collection
+    pub fn from_file(font_path: impl AsRef<Path>) -> Result<FontData> {
+        return FontData::from_file_face(font_path, 0, &Charset::default());
+    }
+
+    /// face_index picks the face inside of a TTC/OTC collection, it has to be 0 for TTF/OTF,
+    /// chars of the charset the font lacks are skipped with a warning
+    pub fn from_file_face(font_path: impl AsRef<Path>, face_index: u32, charset: &Charset) -> Result<FontData> {
+        let font_path = font_path.as_ref();
+        let font_bytes = fs::read(font_path).map_err(Error::io(format!("Failed to read font {}", font_path.display())))?;
+        let face_count = ttf_parser::fonts_in_collection(&font_bytes).unwrap_or(1);
+        if face_index >= face_count {
+            return Err(Error::Font(format!(
+                "Font {} has {} face(s), there is no face {}", font_path.display(), face_count, face_index
+            )));
+        }
+        let font_data = FontData::init(Arc::new(font_bytes), face_index, DEFAULT_SCALE, charset.get_chars())
+            .map_err(|error| Error::Font(format!("{}: {}", font_path.display(), error)))?;
+        let missing: String = charset.get_chars().iter().filter(|char| !font_data.chars.contains(char)).collect();
+        if !missing.is_empty() {
+            eprintln!(
+                "Warning: font {} face {} has no glyphs for {} of {} chars, they will not be recognized: {}",
+                font_path.display(), face_index, missing.chars().count(), charset.get_chars().len(), missing
+            );
+        }
+        return Ok(font_data);
+    }
+
+    pub fn from_location(location: &FontLocation, charset: &Charset) -> Result<FontData> {
+        return FontData::from_file_face(&location.path, location.face_index, charset);
+    }
+
+    /// the same font rendered at another pixel size, like the one of the hardsubs in a 1080p video
+    pub fn at_scale(&self, scale: f32) -> Result<FontData> {
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(Error::Font(format!("Invalid font size {}", scale)));
         }
+        let mut font_data = FontData::init(self.font_bytes.clone(), self.face_index, scale, &self.chars)?;
+        font_data.segmentation = self.segmentation;
+        return Ok(font_data);
+    }
+
+    /// width of a full-width char in pixels, the cell of the Japanese mode
+    pub fn get_cell_width(&self) -> f32 {
+        return self.scale * self.metrics.em;
+    }
+
+    /// height of kanji in pixels, or of the whole em box if the font has none
+    pub fn get_text_height(&self) -> f32 {
+        return self.scale * self.metrics.ideograph_height.unwrap_or(self.metrics.em);
     }
 
-    pub fn get_bitmaps(&self, char: char) -> &[RelMatrix] {
+    /// bitmaps of the char at each size and sub-pixel shift it was rendered with
+    pub(crate) fn get_bitmaps(&self, char: char) -> &[RelMatrix] {
         return self.char_to_shift_to_matrix.get(&char).unwrap();
     }
 }
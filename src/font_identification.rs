@@ -0,0 +1,271 @@
+//! picks the font hardsubs were rendered with among candidate fonts, like all fonts of a
+//! directory: letters segmented from text frames are scored against each candidate with the
+//! same bitmap comparison the OCR uses, the font whose glyphs fit best on average wins,
+//! each candidate is rendered at the size its proportions suggest for these letters
+
+use std::cmp::Ordering;
+use std::path::Path;
+use crate::charset::Charset;
+use crate::cjk_segmentation::{segment_cell_lines, Segmentation};
+use crate::error::{Error, Result};
+use crate::font_data::FontData;
+use crate::font_search::{list_font_files, FontLocation};
+use crate::font_size::SizeSample;
+use crate::frame_region::FrameRegion;
+use crate::match_letter_to_font::get_best_match_score;
+use crate::ocr_out_from_image::{segment_glyphs, segment_pieces, SubsOcrFrame};
+use crate::rel_matrix::RelMatrix;
+
+/// the font is picked once that many letters were sampled
+const MIN_SAMPLE_GLYPHS: usize = 12;
+const MAX_SAMPLE_GLYPHS: usize = 40;
+
+/// or once that many text frames were seen, even if they had fewer letters
+const MAX_SAMPLE_FRAMES: usize = 8;
+
+/// letters of that many pixels or less are too small to tell fonts apart, like commas
+const MIN_GLYPH_HEIGHT: usize = 8;
+
+/// relative to the median letter height, taller ones are likely few letters merged
+/// into one by an outline, much shorter ones are punctuation or bits of noise
+const MIN_GLYPH_HEIGHT_PORTION: f64 = 0.5;
+const MAX_GLYPH_HEIGHT_PORTION: f64 = 1.6;
+const MAX_GLYPH_WIDTH_PORTION: f64 = 1.5;
+
+/// number of the best fonts printed after identification
+const PRINTED_RANKS: usize = 5;
+
+/// a candidate is rendered anew when the estimated size differs by more than that portion
+const RESCALE_TOLERANCE: f32 = 0.02;
+
+pub struct FontCandidate {
+    pub location: FontLocation,
+    pub font_data: FontData,
+}
+
+/// every face of every font file in the directory and its subdirectories, rendered with the
+/// chars of the charset, faces that can not be used for the OCR are skipped with a warning
+pub fn load_candidates(dir: &Path, charset: &Charset) -> Result<Vec<FontCandidate>> {
+    let mut files = Vec::new();
+    list_font_files(dir, 0, &mut files);
+    let mut candidates = Vec::new();
+    for path in files {
+        let face_count = match std::fs::read(&path) {
+            Ok(data) => ttf_parser::fonts_in_collection(&data).unwrap_or(1),
+            Err(_) => 1,
+        };
+        for face_index in 0..face_count {
+            let location = FontLocation { path: path.clone(), face_index };
+            match FontData::from_location(&location, charset) {
+                Ok(font_data) => candidates.push(FontCandidate { location, font_data }),
+                Err(error) => eprintln!("Skipping candidate font: {}", error),
+            }
+        }
+    }
+    if candidates.is_empty() {
+        return Err(Error::Font(format!("No usable fonts found in {}", dir.display())));
+    }
+    return Ok(candidates);
+}
+
+/// letters that were segmented confidently enough to be compared to a font:
+/// of a typical height of the frame and not wide enough to be two letters stuck
+/// together, the lines are kept as they are, only the other letters are left out
+pub fn select_sample_glyphs(lines: Vec<Vec<RelMatrix>>) -> Vec<Vec<RelMatrix>> {
+    let mut heights: Vec<usize> = lines.iter().flatten().map(|glyph| glyph.bounds.get_height()).collect();
+    if heights.is_empty() {
+        return lines;
+    }
+    heights.sort_unstable();
+    let median_height = heights[heights.len() / 2] as f64;
+    let is_sample_glyph = |glyph: &RelMatrix| {
+        let height = glyph.bounds.get_height();
+        let width = glyph.bounds.get_width() as f64;
+        return height >= MIN_GLYPH_HEIGHT
+            && height as f64 >= median_height * MIN_GLYPH_HEIGHT_PORTION
+            && height as f64 <= median_height * MAX_GLYPH_HEIGHT_PORTION
+            && width <= median_height * MAX_GLYPH_WIDTH_PORTION;
+    };
+    return lines.into_iter()
+        .map(|line| line.into_iter().filter(|glyph| is_sample_glyph(glyph)).collect())
+        .collect();
+}
+
+#[derive(Debug, Clone)]
+pub struct FontRank {
+    pub candidate_index: usize,
+    /// match score of each letter with the char it fits best, averaged, the higher the better
+    pub mean_score: f64,
+}
+
+/// all candidates, the best matching first
+pub fn rank_fonts(sample: &[RelMatrix], candidates: &[FontCandidate]) -> Vec<FontRank> {
+    let mut ranking: Vec<FontRank> = candidates.iter()
+        .enumerate()
+        .map(|(candidate_index, candidate)| {
+            let total: f64 = sample.iter()
+                .map(|glyph| get_best_match_score(&glyph.bitmap, &candidate.font_data))
+                .sum();
+            let mean_score = if sample.is_empty() { 0.0 } else { total / sample.len() as f64 };
+            FontRank { candidate_index, mean_score }
+        })
+        .collect();
+    ranking.sort_by(|a, b| b.mean_score.partial_cmp(&a.mean_score).unwrap_or(Ordering::Equal));
+    return ranking;
+}
+
+pub fn print_ranking(ranking: &[FontRank], candidates: &[FontCandidate], sample_size: usize) {
+    eprintln!("Fonts ranked by mean match score of {} letters:", sample_size);
+    for (place, rank) in ranking.iter().take(PRINTED_RANKS).enumerate() {
+        let location = &candidates[rank.candidate_index].location;
+        eprintln!("  {}. {:.4} {} face {}", place + 1, rank.mean_score, location.path.display(), location.face_index);
+    }
+}
+
+/// re-renders the candidate at the size estimated from the letters, if it changed noticeably
+fn fit_size(candidate: &mut FontCandidate, sizes: &SizeSample) {
+    let scale = match sizes.estimate_scale(&candidate.font_data.metrics) {
+        Some(scale) => scale,
+        None => return,
+    };
+    if (scale / candidate.font_data.scale - 1.0).abs() <= RESCALE_TOLERANCE {
+        return;
+    }
+    match candidate.font_data.at_scale(scale) {
+        Ok(font_data) => candidate.font_data = font_data,
+        Err(error) => eprintln!("Failed to render {} at {:.1} px: {}", candidate.location.path.display(), scale, error),
+    }
+}
+
+fn fit_sizes(candidates: &mut [FontCandidate], sizes: &SizeSample) {
+    for candidate in candidates.iter_mut() {
+        fit_size(candidate, sizes);
+    }
+}
+
+/// font to OCR with: either given upfront or identified among the candidates from letters
+/// of the first text frames, the size is estimated from the same letters, so a single
+/// candidate still needs them, frames should not be OCR-ed till the font is picked,
+/// since the first guesses may be in a wrong font and at a wrong size
+pub enum FontSelection {
+    Known(FontData),
+    Identifying {
+        candidates: Vec<FontCandidate>,
+        /// letters of each text frame, or pieces of strokes in the Japanese mode, since
+        /// they are cut into cells of the font size, which is not known yet
+        frames: Vec<Vec<RelMatrix>>,
+        glyph_count: usize,
+        sizes: SizeSample,
+    },
+}
+
+/// letters or pieces of strokes of a frame, see FontSelection::Identifying, with the count of letters
+fn sample_frame(ocr_frame: &SubsOcrFrame, region: &FrameRegion, font_data: &FontData, sizes: &mut SizeSample) -> (Vec<RelMatrix>, usize) {
+    return match font_data.segmentation {
+        Segmentation::Letters => {
+            let lines = select_sample_glyphs(segment_glyphs(ocr_frame, region, font_data));
+            sizes.add_frame(&lines);
+            let glyphs: Vec<RelMatrix> = lines.into_iter().flatten().collect();
+            let glyph_count = glyphs.len();
+            (glyphs, glyph_count)
+        },
+        Segmentation::FullWidthCells { .. } => {
+            let pieces = segment_pieces(ocr_frame, region);
+            sizes.add_cell_frame(pieces.clone());
+            // cells cut at the default size are off, but good enough to count them
+            let glyph_count = select_sample_glyphs(cut_cells(pieces.clone(), font_data)).iter().map(Vec::len).sum();
+            (pieces, glyph_count)
+        },
+    };
+}
+
+fn cut_cells(pieces: Vec<RelMatrix>, font_data: &FontData) -> Vec<Vec<RelMatrix>> {
+    return segment_cell_lines(pieces, font_data, true)
+        .into_iter()
+        .map(|line| line.cells)
+        .collect();
+}
+
+/// renders each candidate at the size estimated from all sampled frames and scores
+/// the letters against them, once, so neither is repeated as frames come
+fn pick_font(mut candidates: Vec<FontCandidate>, frames: Vec<Vec<RelMatrix>>, sizes: &SizeSample) -> FontData {
+    fit_sizes(&mut candidates, sizes);
+
+    let mut leader = 0;
+    if candidates.len() > 1 {
+        let sample: Vec<RelMatrix> = match candidates[0].font_data.segmentation {
+            Segmentation::Letters => frames.into_iter().flatten().take(MAX_SAMPLE_GLYPHS).collect(),
+            // all candidates were fitted to the same text, so their cells are about the same
+            Segmentation::FullWidthCells { .. } => frames.into_iter()
+                .flat_map(|pieces| select_sample_glyphs(cut_cells(pieces, &candidates[0].font_data)).into_iter().flatten())
+                .take(MAX_SAMPLE_GLYPHS)
+                .collect(),
+        };
+        if sample.is_empty() {
+            eprintln!("No letters seen to identify the font by, taking the first candidate");
+        } else {
+            let ranking = rank_fonts(&sample, &candidates);
+            print_ranking(&ranking, &candidates, sample.len());
+            leader = ranking[0].candidate_index;
+        }
+    }
+    let chosen = candidates.swap_remove(leader);
+    eprintln!(
+        "Using font {} face {} at {:.1} px",
+        chosen.location.path.display(), chosen.location.face_index, chosen.font_data.scale
+    );
+    return chosen.font_data;
+}
+
+impl FontSelection {
+    pub fn identify_among(candidates: Vec<FontCandidate>) -> FontSelection {
+        return FontSelection::Identifying { candidates, frames: Vec::new(), glyph_count: 0, sizes: SizeSample::default() };
+    }
+
+    /// the font is known, but the size of the hardsubs is not
+    pub fn estimate_size(candidate: FontCandidate) -> FontSelection {
+        return FontSelection::identify_among(vec![candidate]);
+    }
+
+    /// None till the font is picked
+    pub fn get_known(&self) -> Option<&FontData> {
+        return match self {
+            FontSelection::Known(font_data) => Some(font_data),
+            FontSelection::Identifying { .. } => None,
+        };
+    }
+
+    /// letters of a text frame are added to the sample, the font is picked once there
+    /// are enough of them, or enough frames were seen, so that frames waiting for the OCR
+    /// do not pile up when text is short
+    pub fn add_frame(&mut self, ocr_frame: &SubsOcrFrame, region: &FrameRegion) {
+        if let FontSelection::Identifying { candidates, frames, glyph_count, sizes } = self {
+            let (frame, frame_glyph_count) = sample_frame(ocr_frame, region, &candidates[0].font_data, sizes);
+            frames.push(frame);
+            *glyph_count += frame_glyph_count;
+            if *glyph_count >= MIN_SAMPLE_GLYPHS || frames.len() >= MAX_SAMPLE_FRAMES {
+                self.finish();
+            }
+        }
+    }
+
+    /// picks the font with whatever letters were sampled, like when the input ended before
+    /// there were enough of them, the ranking is printed either way
+    pub fn finish(&mut self) -> &FontData {
+        if let FontSelection::Identifying { candidates, frames, sizes, .. } = self {
+            let font_data = pick_font(std::mem::take(candidates), std::mem::take(frames), sizes);
+            *self = FontSelection::Known(font_data);
+        }
+        return match self {
+            FontSelection::Known(font_data) => font_data,
+            FontSelection::Identifying { .. } => unreachable!("the font was just picked"),
+        };
+    }
+
+    /// the font for a single frame, like in the ocr subcommand, picked
+    /// from the letters of this frame if it was not known yet
+    pub fn get_font(&mut self, ocr_frame: &SubsOcrFrame, region: &FrameRegion) -> &FontData {
+        self.add_frame(ocr_frame, region);
+        return self.finish();
+    }
+}
@@ -0,0 +1,138 @@
+//! finds the font file of a family, like "Trebuchet MS", among the fonts installed in
+//! the system or in given directories, so that the OCR can be pointed at a font by its
+//! name rather than by a path that differs from one machine to another
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use ttf_parser::name_id;
+use crate::error::{Error, Result};
+
+const FONT_EXTENSIONS: &[&str] = &["ttf", "otf", "ttc", "otc"];
+
+/// font directories are nested by foundry and format on Linux, symlinks could make it endless
+const MAX_DIR_DEPTH: usize = 8;
+
+/// subfamily names of the face that is used when only the family is asked for
+const REGULAR_SUBFAMILIES: &[&str] = &["Regular", "Book", "Normal", "Roman"];
+
+/// a face inside of a font file, index is above 0 only in TTC/OTC collections
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontLocation {
+    pub path: PathBuf,
+    pub face_index: u32,
+}
+
+/// where the fonts are installed on Linux, macOS and Windows, the ones that
+/// do not exist on this machine are skipped during the search anyway
+pub fn get_system_font_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = ["/usr/share/fonts", "/usr/local/share/fonts", "/Library/Fonts", "/System/Library/Fonts"]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+    if let Some(home) = env::var_os("HOME") {
+        let home = PathBuf::from(home);
+        dirs.push(home.join(".fonts"));
+        dirs.push(home.join(".local/share/fonts"));
+        dirs.push(home.join("Library/Fonts"));
+    }
+    if let Some(windows) = env::var_os("WINDIR") {
+        dirs.push(PathBuf::from(windows).join("Fonts"));
+    }
+    if let Some(local_app_data) = env::var_os("LOCALAPPDATA") {
+        dirs.push(PathBuf::from(local_app_data).join("Microsoft\\Windows\\Fonts"));
+    }
+    return dirs;
+}
+
+fn is_font_file(path: &Path) -> bool {
+    return match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => FONT_EXTENSIONS.contains(&extension.to_lowercase().as_str()),
+        None => false,
+    };
+}
+
+/// font files in the directory and its subdirectories, sorted by path
+pub(crate) fn list_font_files(dir: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    if depth > MAX_DIR_DEPTH {
+        return;
+    }
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect(),
+        Err(_) => return,
+    };
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            list_font_files(&path, depth + 1, files);
+        } else if is_font_file(&path) {
+            files.push(path);
+        }
+    }
+}
+
+fn get_names(face: &ttf_parser::Face, ids: &[u16]) -> Vec<String> {
+    return face.names()
+        .into_iter()
+        .filter(|name| ids.contains(&name.name_id))
+        .filter_map(|name| name.to_string())
+        .collect();
+}
+
+/// how well the face fits the asked name: 2 for the full name, like "DejaVu Sans Bold",
+/// 1 for the regular face of the family, 0 for other faces of the family, None if neither
+fn get_match_rank(face: &ttf_parser::Face, family: &str) -> Option<u8> {
+    let is_same = |name: &String| name.trim().eq_ignore_ascii_case(family.trim());
+    if get_names(face, &[name_id::FULL_NAME]).iter().any(is_same) {
+        return Some(2);
+    }
+    if !get_names(face, &[name_id::FAMILY, name_id::TYPOGRAPHIC_FAMILY]).iter().any(is_same) {
+        return None;
+    }
+    let is_regular = get_names(face, &[name_id::SUBFAMILY, name_id::TYPOGRAPHIC_SUBFAMILY])
+        .iter()
+        .any(|subfamily| REGULAR_SUBFAMILIES.iter().any(|regular| subfamily.eq_ignore_ascii_case(regular)));
+    return Some(if is_regular { 1 } else { 0 });
+}
+
+/// looks through every face of every font file in the directories, in the order of the
+/// directories, the family name is compared case-insensitively, a full face name like
+/// "Verdana Bold" picks that exact face, otherwise the regular face of the family is preferred
+pub fn find_font(family: &str, dirs: &[PathBuf]) -> Result<FontLocation> {
+    let mut best: Option<(u8, FontLocation)> = None;
+    for dir in dirs {
+        let mut files = Vec::new();
+        list_font_files(dir, 0, &mut files);
+        for path in files {
+            let data = match fs::read(&path) {
+                Ok(data) => data,
+                Err(_) => continue,
+            };
+            let face_count = ttf_parser::fonts_in_collection(&data).unwrap_or(1);
+            for face_index in 0..face_count {
+                let face = match ttf_parser::Face::parse(&data, face_index) {
+                    Ok(face) => face,
+                    Err(_) => continue,
+                };
+                let rank = match get_match_rank(&face, family) {
+                    Some(rank) => rank,
+                    None => continue,
+                };
+                if best.as_ref().map_or(true, |(best_rank, _)| rank > *best_rank) {
+                    best = Some((rank, FontLocation { path: path.clone(), face_index }));
+                }
+                if rank == 2 {
+                    return Ok(best.unwrap().1);
+                }
+            }
+        }
+    }
+    return match best {
+        Some((_, location)) => Ok(location),
+        None => Err(Error::Font(format!(
+            "No font of family {:?} found in {}",
+            family,
+            dirs.iter().map(|dir| dir.display().to_string()).collect::<Vec<_>>().join(", ")
+        ))),
+    };
+}
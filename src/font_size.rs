@@ -0,0 +1,189 @@
+//! guesses the pixel size hardsubs were rendered at, so that the font gets rendered at the
+//! same size and the same font works for a 480p and a 1080p video alike: heights of lowercase
+//! and capital letters and the distance between lines are measured in the frames and
+//! compared to the same proportions of the font
+
+use std::cmp::Ordering;
+use crate::cjk_segmentation::{group_pieces_by_line, guess_text_height};
+use crate::font_data::FontMetrics;
+use crate::rel_matrix::RelMatrix;
+
+/// a frame with fewer letters than that can not tell lowercase letters from capitals
+const MIN_FRAME_GLYPHS: usize = 4;
+
+/// letters this much taller than the lowest quarter of letters are capitals or have ascenders
+const TALL_GLYPH_PORTION: f32 = 1.2;
+
+/// with fewer tall letters than that the text is taken as written in all caps...
+const MIN_TALL_GLYPHS_SHARE: f32 = 0.1;
+/// ...if it has that many letters at least, short lowercase text like "a sun"
+/// or "car" has no tall letters either and its heights are skipped
+const MIN_ALL_CAPS_GLYPHS: usize = 12;
+
+/// relative to the x-height, letters whose bottom is lower than the baseline
+/// by more than that are descenders, like "g" or "y", their height tells nothing
+const BASELINE_TOLERANCE_PORTION: f32 = 0.15;
+
+/// lines with fewer letters do not give a reliable baseline
+const MIN_LINE_GLYPHS: usize = 3;
+
+/// relative to the tallest line of a Japanese frame, lower lines are ruby or
+/// consist of punctuation only, like "……", and tell nothing about the size
+const MIN_CELL_LINE_HEIGHT_PORTION: f32 = 0.7;
+
+/// antialiased top and bottom edges make a letter about a pixel taller than its outline
+const GLYPH_EDGE_PX: f32 = 1.0;
+
+/// average of the two middle values if there is an even number of them
+fn get_median(values: &[f32]) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let middle = sorted.len() / 2;
+    return Some(if sorted.len() % 2 == 0 {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    } else {
+        sorted[middle]
+    });
+}
+
+/// pixel sizes measured in the letters of text frames
+#[derive(Debug, Clone, Default)]
+pub struct SizeSample {
+    x_heights: Vec<f32>,
+    cap_heights: Vec<f32>,
+    /// of the lines of Japanese text, the tallest kanji or kana defines it
+    ideograph_heights: Vec<f32>,
+    line_pitches: Vec<f32>,
+}
+
+impl SizeSample {
+    /// letters of one frame by line, like segment_glyphs() returns them, better
+    /// without punctuation, since the baseline of each line is measured
+    pub fn add_frame(&mut self, lines: &[Vec<RelMatrix>]) {
+        let heights: Vec<f32> = lines.iter().flatten().map(|glyph| glyph.bounds.get_height() as f32).collect();
+        if heights.len() < MIN_FRAME_GLYPHS {
+            return;
+        }
+        let mut sorted_heights = heights.clone();
+        sorted_heights.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        let lower_quartile = sorted_heights[sorted_heights.len() / 4];
+        let tall_count = heights.iter().filter(|height| **height >= lower_quartile * TALL_GLYPH_PORTION).count();
+        if (tall_count as f32) < heights.len() as f32 * MIN_TALL_GLYPHS_SHARE {
+            if heights.len() >= MIN_ALL_CAPS_GLYPHS {
+                self.cap_heights.extend(heights);
+            }
+            return;
+        }
+
+        let tolerance = (lower_quartile * BASELINE_TOLERANCE_PORTION).round().max(1.0) as i64;
+        let mut baselines = Vec::new();
+        for line in lines {
+            if line.len() < MIN_LINE_GLYPHS {
+                continue;
+            }
+            let bottoms: Vec<f32> = line.iter().map(|glyph| glyph.bounds.end.y as f32).collect();
+            let baseline = get_median(&bottoms).unwrap().round() as i64;
+            baselines.push(baseline);
+            for glyph in line {
+                if (glyph.bounds.end.y - baseline).abs() > tolerance {
+                    continue;
+                }
+                let height = glyph.bounds.get_height() as f32;
+                if height >= lower_quartile * TALL_GLYPH_PORTION {
+                    self.cap_heights.push(height);
+                } else {
+                    self.x_heights.push(height);
+                }
+            }
+        }
+        baselines.sort_unstable();
+        for pair in baselines.windows(2) {
+            self.line_pitches.push((pair[1] - pair[0]) as f32);
+        }
+    }
+
+    /// connected pieces of a frame of Japanese text, like segment_pieces() returns them, kanji
+    /// are cut into strokes, so heights of whole lines are measured instead of single letters
+    pub fn add_cell_frame(&mut self, pieces: Vec<RelMatrix>) {
+        let text_height = guess_text_height(&pieces);
+        let lines = group_pieces_by_line(pieces, text_height);
+        let max_height = lines.iter().map(|line| line.bounds.get_height()).max().unwrap_or(0) as f32;
+        let mut bottoms = Vec::new();
+        for line in lines {
+            let height = line.bounds.get_height() as f32;
+            if height >= max_height * MIN_CELL_LINE_HEIGHT_PORTION {
+                self.ideograph_heights.push(height);
+                bottoms.push(line.bounds.end.y);
+            }
+        }
+        for pair in bottoms.windows(2) {
+            self.line_pitches.push((pair[1] - pair[0]) as f32);
+        }
+    }
+
+    /// pixel size to render the font at, the median of the sizes told by the x-height, the
+    /// cap height, the kanji height and the line pitch, None if nothing was measured
+    pub fn estimate_scale(&self, metrics: &FontMetrics) -> Option<f32> {
+        let estimates: Vec<f32> = [
+            get_median(&self.x_heights).and_then(|x_height| Some((x_height - GLYPH_EDGE_PX) / metrics.x_height?)),
+            get_median(&self.cap_heights).and_then(|cap_height| Some((cap_height - GLYPH_EDGE_PX) / metrics.cap_height?)),
+            get_median(&self.ideograph_heights)
+                .and_then(|ideograph_height| Some((ideograph_height - GLYPH_EDGE_PX) / metrics.ideograph_height?)),
+            get_median(&self.line_pitches).map(|line_pitch| line_pitch / metrics.line_pitch),
+        ].iter().filter_map(|estimate| *estimate).collect();
+        return get_median(&estimates);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixel_utils::Point;
+    use crate::rel_matrix::Bounds;
+
+    const METRICS: FontMetrics = FontMetrics { x_height: Some(0.5), cap_height: Some(0.7), line_pitch: 1.2, ideograph_height: None, em: 1.0 };
+
+    fn glyph(x: i64, top: i64, bottom: i64) -> RelMatrix {
+        return RelMatrix { bounds: Bounds { start: Point { x, y: top }, end: Point { x: x + 10, y: bottom } }, bitmap: Vec::new() };
+    }
+
+    /// letters of a line rendered at 30 px: "x" is 15 px and "H" is 21 px tall, plus the edge
+    /// pixel, "g" goes below the baseline, the next line is 36 px lower
+    fn line_at_30px(baseline: i64) -> Vec<RelMatrix> {
+        return vec![
+            glyph(0, baseline - 21, baseline),
+            glyph(12, baseline - 15, baseline),
+            glyph(24, baseline - 15, baseline),
+            glyph(36, baseline - 15, baseline + 7),
+            glyph(48, baseline - 15, baseline),
+        ];
+    }
+
+    #[test]
+    fn estimates_scale_from_letter_heights_and_line_pitch() {
+        let mut sample = SizeSample::default();
+        sample.add_frame(&[line_at_30px(100), line_at_30px(136)]);
+        assert_eq!(sample.line_pitches, vec![36.0]);
+        let scale = sample.estimate_scale(&METRICS).unwrap();
+        assert!((scale - 30.0).abs() < 0.01, "{}", scale);
+    }
+
+    #[test]
+    fn descenders_are_not_measured() {
+        let mut sample = SizeSample::default();
+        sample.add_frame(&[line_at_30px(100)]);
+        assert_eq!(sample.x_heights, vec![16.0; 3]);
+        assert_eq!(sample.cap_heights, vec![22.0]);
+    }
+
+    #[test]
+    fn short_text_without_tall_letters_tells_nothing() {
+        let mut sample = SizeSample::default();
+        sample.add_frame(&[(0..5).map(|i| glyph(i * 12, 85, 100)).collect()]);
+        sample.add_frame(&[vec![glyph(0, 79, 100), glyph(12, 85, 100)]]);
+        assert!(sample.estimate_scale(&METRICS).is_none());
+    }
+}
@@ -0,0 +1,148 @@
+//! cheap first stage of the frame comparison: a checksum of each BLOCK_SIZE x BLOCK_SIZE
+//! block of the frame, only blocks with a different checksum go to the full RGB diff,
+//! so long static stretches of a recording cost one pass over the bytes per frame
+
+use std::ops::Range;
+use crate::blink_mask::BLOCK_SIZE;
+
+const CHECKSUM_SEED: u64 = 0xcbf2_9ce4_8422_2325;
+const CHECKSUM_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+pub struct BlockGrid {
+    pub width: usize,
+    pub height: usize,
+    pub cols: usize,
+    pub rows: usize,
+}
+
+impl BlockGrid {
+    pub fn new(width: usize, height: usize) -> BlockGrid {
+        return BlockGrid {
+            width,
+            height,
+            cols: (width + BLOCK_SIZE - 1) / BLOCK_SIZE,
+            rows: (height + BLOCK_SIZE - 1) / BLOCK_SIZE,
+        };
+    }
+
+    pub fn block_count(&self) -> usize {
+        return self.cols * self.rows;
+    }
+
+    pub fn get_block_index(&self, x: usize, y: usize) -> usize {
+        return y / BLOCK_SIZE * self.cols + x / BLOCK_SIZE;
+    }
+
+    /// x and y pixel ranges covered by the block, blocks on the right and bottom edges may be smaller
+    pub fn get_block_ranges(&self, block_index: usize) -> (Range<usize>, Range<usize>) {
+        let x = block_index % self.cols * BLOCK_SIZE;
+        let y = block_index / self.cols * BLOCK_SIZE;
+        return (x..(x + BLOCK_SIZE).min(self.width), y..(y + BLOCK_SIZE).min(self.height));
+    }
+}
+
+/// for each pixel whether it is taken into account, along with number of such
+/// pixels in each block, so that they are not counted again on every frame
+pub struct PixelMask {
+    pub pixels: Vec<bool>,
+    pub block_region_pixels: Vec<u32>,
+    pub region_pixels: usize,
+    /// pixels the scene brightness is measured over, the whole frame except the ignored parts,
+    /// since text filling a tight region would move its histogram as much as a cut does
+    pub scene_pixels: Vec<bool>,
+}
+
+impl PixelMask {
+    pub fn new(pixels: Vec<bool>, scene_pixels: Vec<bool>, width: usize, height: usize) -> PixelMask {
+        let grid = BlockGrid::new(width, height);
+        let mut block_region_pixels = vec![0; grid.block_count()];
+        let mut region_pixels = 0;
+        for (pixel_index, is_considered) in pixels.iter().enumerate() {
+            if *is_considered {
+                block_region_pixels[grid.get_block_index(pixel_index % width, pixel_index / width)] += 1;
+                region_pixels += 1;
+            }
+        }
+        return PixelMask { pixels, block_region_pixels, region_pixels, scene_pixels };
+    }
+}
+
+/// FNV-like hash of R,G,B bytes of each block, eight bytes at a time
+pub fn get_block_checksums(bitmap: &[u8], width: usize, height: usize) -> Vec<u64> {
+    let grid = BlockGrid::new(width, height);
+    let mut checksums = vec![CHECKSUM_SEED; grid.block_count()];
+    for y in 0..height {
+        let row_start = y * width * 3;
+        let block_row_index = y / BLOCK_SIZE * grid.cols;
+        for col in 0..grid.cols {
+            let start = row_start + col * BLOCK_SIZE * 3;
+            let end = row_start + ((col + 1) * BLOCK_SIZE).min(width) * 3;
+            let checksum = &mut checksums[block_row_index + col];
+            for chunk in bitmap[start..end].chunks(8) {
+                let mut word = [0; 8];
+                word[..chunk.len()].copy_from_slice(chunk);
+                *checksum = (*checksum ^ u64::from_le_bytes(word)).wrapping_mul(CHECKSUM_PRIME);
+            }
+        }
+    }
+    return checksums;
+}
+
+pub fn get_changed_blocks(old_checksums: &[u64], new_checksums: &[u64]) -> Vec<bool> {
+    return old_checksums.iter()
+        .zip(new_checksums)
+        .map(|(old_checksum, new_checksum)| old_checksum != new_checksum)
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 3x2 blocks, the right and bottom ones are narrower
+    const WIDTH: usize = 40;
+    const HEIGHT: usize = 20;
+
+    fn set_pixel(bitmap: &mut [u8], x: usize, y: usize) {
+        let byte_index = (y * WIDTH + x) * 3;
+        bitmap[byte_index..byte_index + 3].copy_from_slice(&[255, 255, 255]);
+    }
+
+    #[test]
+    fn grid_covers_partial_blocks() {
+        let grid = BlockGrid::new(WIDTH, HEIGHT);
+        assert_eq!((grid.cols, grid.rows, grid.block_count()), (3, 2, 6));
+        assert_eq!(grid.get_block_index(39, 19), 5);
+        assert_eq!(grid.get_block_ranges(5), (32..40, 16..20));
+    }
+
+    #[test]
+    fn only_blocks_with_changed_pixels_differ() {
+        let old_bitmap = vec![100; WIDTH * HEIGHT * 3];
+        let mut new_bitmap = old_bitmap.clone();
+        set_pixel(&mut new_bitmap, 17, 3);
+        set_pixel(&mut new_bitmap, 39, 19);
+        let changed = get_changed_blocks(
+            &get_block_checksums(&old_bitmap, WIDTH, HEIGHT),
+            &get_block_checksums(&new_bitmap, WIDTH, HEIGHT),
+        );
+        assert_eq!(changed, vec![false, true, false, false, false, true]);
+    }
+
+    #[test]
+    fn same_pixels_at_other_place_of_block_differ() {
+        let mut a = vec![100; WIDTH * HEIGHT * 3];
+        let mut b = a.clone();
+        set_pixel(&mut a, 0, 0);
+        set_pixel(&mut b, 1, 0);
+        assert_ne!(get_block_checksums(&a, WIDTH, HEIGHT)[0], get_block_checksums(&b, WIDTH, HEIGHT)[0]);
+    }
+
+    #[test]
+    fn mask_counts_region_pixels_per_block() {
+        let pixels: Vec<bool> = (0..WIDTH * HEIGHT).map(|pixel_index| pixel_index % WIDTH >= 32).collect();
+        let mask = PixelMask::new(pixels, vec![true; WIDTH * HEIGHT], WIDTH, HEIGHT);
+        assert_eq!(mask.region_pixels, 8 * 20);
+        assert_eq!(mask.block_region_pixels, vec![0, 0, 128, 0, 0, 32]);
+    }
+}
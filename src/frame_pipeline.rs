@@ -0,0 +1,138 @@
+//! spreads comparison of each next frame to the previous one over a pool of threads,
+//! while everything that depends on the previous frames, like text animation or learned
+//! blinks, is still done in frame order on the calling thread
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use crate::detect_still_frames::{analyze_frame, ChangeTracker, DetectionParams, FrameAnalysis, FrameChange};
+use crate::frame_blocks::PixelMask;
+use crate::rel_matrix::Bounds;
+use crate::rgb_frame::RgbFrame;
+
+/// frames handed to the pool and not yet processed in order, per thread, limits memory
+/// usage when one slow frame holds back results of all frames after it
+const FRAMES_IN_FLIGHT_PER_THREAD: usize = 4;
+
+struct AnalysisJob {
+    seq: usize,
+    last_frame: Option<Arc<RgbFrame>>,
+    frame: Arc<RgbFrame>,
+    pixel_mask: Arc<PixelMask>,
+    is_same_mask: bool,
+}
+
+pub fn get_thread_count(requested: usize) -> usize {
+    if requested > 0 {
+        return requested;
+    }
+    return thread::available_parallelism().map(|count| count.get()).unwrap_or(1);
+}
+
+/// workers finish frames in arbitrary order, results wait here till all frames before them are done
+struct ReorderBuffer {
+    pending: BTreeMap<usize, FrameAnalysis>,
+    next_seq: usize,
+}
+
+impl ReorderBuffer {
+    fn pop_ready(&mut self) -> Option<FrameAnalysis> {
+        let analysis = self.pending.remove(&self.next_seq)?;
+        self.next_seq += 1;
+        return Some(analysis);
+    }
+}
+
+/// take analysis results from the pool till there are at most max_in_flight frames not processed,
+/// results that are ready by then are processed too, so that nothing waits for the next frame
+fn drain_results(
+    results: &Receiver<(usize, FrameAnalysis)>,
+    reorder: &mut ReorderBuffer,
+    sent: usize,
+    max_in_flight: usize,
+    tracker: &mut ChangeTracker,
+    on_change: &mut impl FnMut(FrameChange),
+) {
+    loop {
+        let (seq, analysis) = if sent - reorder.next_seq > max_in_flight {
+            results.recv().expect("frame analysis thread died")
+        } else {
+            match results.try_recv() {
+                Ok(result) => result,
+                Err(_) => return,
+            }
+        };
+        reorder.pending.insert(seq, analysis);
+        while let Some(analysis) = reorder.pop_ready() {
+            tracker.process(analysis, on_change);
+        }
+    }
+}
+
+/// feed the frames through ChangeTracker, returns blocks that were learned to be blinking,
+/// pixel mask changes take effect from the frame after the one they were learned on
+pub fn process_frames(
+    frames: impl Iterator<Item = RgbFrame>,
+    params: &DetectionParams,
+    on_change: &mut impl FnMut(FrameChange),
+) -> Vec<Bounds> {
+    let threads = get_thread_count(params.threads);
+    let max_in_flight = threads * FRAMES_IN_FLIGHT_PER_THREAD;
+    let mut tracker = ChangeTracker::new(params);
+
+    let (job_sender, job_receiver) = channel::<AnalysisJob>();
+    let (result_sender, result_receiver) = channel();
+    let job_receiver = Mutex::new(job_receiver);
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let job_receiver = &job_receiver;
+            let result_sender = result_sender.clone();
+            scope.spawn(move || loop {
+                let job = match job_receiver.lock().unwrap().recv() {
+                    Ok(job) => job,
+                    Err(_) => break,
+                };
+                let analysis = analyze_frame(
+                    job.last_frame.as_deref(), job.frame, params, &job.pixel_mask, job.is_same_mask,
+                );
+                if result_sender.send((job.seq, analysis)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(result_sender);
+
+        let mut reorder = ReorderBuffer { pending: BTreeMap::new(), next_seq: 0 };
+        let mut sent = 0;
+        let mut last_frame: Option<Arc<RgbFrame>> = None;
+        let mut last_mask: Option<Arc<PixelMask>> = None;
+        for frame in frames {
+            let frame = Arc::new(frame);
+            let last_frame = last_frame.replace(frame.clone()).filter(|last| last.is_same_size(&frame));
+            if last_frame.is_none() {
+                // pixel mask is about to be remade for the new size, frames of the old size come first
+                drain_results(&result_receiver, &mut reorder, sent, 0, &mut tracker, on_change);
+            }
+            tracker.ensure_frame_size(&frame);
+            let pixel_mask = tracker.pixel_mask.clone();
+            let is_same_mask = match last_mask.replace(pixel_mask.clone()) {
+                Some(last_mask) => Arc::ptr_eq(&last_mask, &pixel_mask),
+                None => false,
+            };
+            job_sender.send(AnalysisJob {
+                seq: sent,
+                last_frame,
+                frame,
+                pixel_mask,
+                is_same_mask,
+            }).unwrap();
+            sent += 1;
+            drain_results(&result_receiver, &mut reorder, sent, max_in_flight, &mut tracker, on_change);
+        }
+        drop(job_sender);
+        drain_results(&result_receiver, &mut reorder, sent, 0, &mut tracker, on_change);
+    });
+    tracker.finish(on_change);
+    return tracker.get_blinking_regions();
+}
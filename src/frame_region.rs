@@ -0,0 +1,133 @@
+//! rectangles of the frame where hardsub text is expected, like the text panel of a visual
+//! novel, so that character sprites and backgrounds outside of them are not considered,
+//! and parts of the frame that must be ignored, like the blinking auto-play indicator
+
+use crate::pixel_utils::Point;
+use crate::ppm_format::PpmData;
+use crate::rel_matrix::Bounds;
+
+/// pixels set in a mask image, every pixel that is not pure black is ignored
+#[derive(Debug, Clone)]
+pub struct IgnoreMask {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<bool>,
+}
+
+impl IgnoreMask {
+    pub fn from_ppm(ppm: &PpmData) -> IgnoreMask {
+        let pixels = ppm.get_bitmap()
+            .chunks(3)
+            .map(|rgb| rgb != [0, 0, 0])
+            .collect();
+        return IgnoreMask { width: ppm.width, height: ppm.height, pixels };
+    }
+
+    fn is_set(&self, point: &Point) -> bool {
+        let x = point.x as usize;
+        let y = point.y as usize;
+        return x < self.width && y < self.height && self.pixels[y * self.width + x];
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FrameRegion {
+    /// empty means the whole frame
+    pub rects: Vec<Bounds>,
+    /// taken out of rects
+    pub ignore_rects: Vec<Bounds>,
+    pub ignore_mask: Option<IgnoreMask>,
+}
+
+fn is_in_rect(point: &Point, rect: &Bounds) -> bool {
+    return point.x >= rect.start.x && point.x <= rect.end.x
+        && point.y >= rect.start.y && point.y <= rect.end.y;
+}
+
+impl FrameRegion {
+    pub fn whole_frame() -> FrameRegion {
+        return FrameRegion::default();
+    }
+
+    pub fn is_whole_frame(&self) -> bool {
+        return self.rects.is_empty() && self.ignore_rects.is_empty() && self.ignore_mask.is_none();
+    }
+
+    pub fn is_ignored(&self, point: &Point) -> bool {
+        if self.ignore_rects.iter().any(|rect| is_in_rect(point, rect)) {
+            return true;
+        }
+        return match &self.ignore_mask {
+            Some(mask) => mask.is_set(point),
+            None => false,
+        };
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        if self.is_whole_frame() {
+            return true;
+        }
+        let is_included = self.rects.is_empty()
+            || self.rects.iter().any(|rect| is_in_rect(point, rect));
+        return is_included && !self.is_ignored(point);
+    }
+
+    /// contains() for every pixel of the frame, row by row, so that
+    /// it would not be re-calculated for every pixel of every frame
+    pub fn make_pixel_mask(&self, width: usize, height: usize) -> Vec<bool> {
+        let mut mask = Vec::with_capacity(width * height);
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                mask.push(self.contains(&Point { x, y }));
+            }
+        }
+        return mask;
+    }
+
+    /// same as make_pixel_mask, but only the ignored parts are taken out, rects do not matter
+    pub fn make_scene_pixel_mask(&self, width: usize, height: usize) -> Vec<bool> {
+        let mut mask = Vec::with_capacity(width * height);
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                mask.push(!self.is_ignored(&Point { x, y }));
+            }
+        }
+        return mask;
+    }
+}
+
+/// parses "x,y,width,height" as it is passed in command line
+pub fn parse_rect(value: &str) -> Result<Bounds, String> {
+    let numbers: Vec<i64> = value
+        .split(',')
+        .map(|part| part.trim().parse::<i64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("expected x,y,width,height, got {}", value))?;
+    if numbers.len() != 4 || numbers[2] <= 0 || numbers[3] <= 0 || numbers[0] < 0 || numbers[1] < 0 {
+        return Err(format!("expected x,y,width,height, got {}", value));
+    }
+    return Ok(Bounds {
+        start: Point { x: numbers[0], y: numbers[1] },
+        end: Point { x: numbers[0] + numbers[2] - 1, y: numbers[1] + numbers[3] - 1 },
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rect_into_inclusive_bounds() {
+        let bounds = parse_rect("10,20,30,40").unwrap();
+        assert_eq!(bounds.start, Point { x: 10, y: 20 });
+        assert_eq!(bounds.end, Point { x: 39, y: 59 });
+        assert_eq!(parse_rect(" 0, 0, 1, 1 ").unwrap().end, Point { x: 0, y: 0 });
+    }
+
+    #[test]
+    fn rejects_malformed_rects() {
+        for value in ["", "1,2,3", "1,2,3,4,5", "a,b,c,d", "1,2,0,4", "1,2,3,0", "-1,2,3,4", "1,-2,3,4", "1.5,2,3,4"].iter() {
+            assert!(parse_rect(value).is_err(), "{:?} should be rejected", value);
+        }
+    }
+}
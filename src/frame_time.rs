@@ -0,0 +1,190 @@
+//! frame timestamps taken from the decoded stream: PTS multiplied by the stream
+//! time_base gives correct time for any frame rate, including variable one
+
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::Rational;
+use serde::Serialize;
+
+/// position of a frame in the video, both as its index and as time from the file start
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub struct FrameTime {
+    pub frame_index: usize,
+    /// in stream time_base units, None if container did not provide it for this frame
+    pub pts: Option<i64>,
+    pub millis: u64,
+}
+
+/// used when neither PTS nor stream frame rate is known, that is what the test recording had
+const FALLBACK_FRAME_RATE: f64 = 24.390244;
+
+/// assigns FrameTime to each next decoded frame
+pub struct FrameClock {
+    time_base: Rational,
+    start_pts: Option<i64>,
+    frame_duration_ms: f64,
+    next_index: usize,
+    last_millis: Option<u64>,
+    /// after a seek, index of the next frame is unknown till a frame with PTS arrives
+    is_index_lost: bool,
+}
+
+impl FrameClock {
+    /// start_time is the stream start_time(), ffmpeg reports i64::MIN (AV_NOPTS_VALUE) when unknown
+    pub fn new(time_base: Rational, start_time: i64, avg_frame_rate: Rational) -> FrameClock {
+        let frame_rate = if avg_frame_rate.numerator() > 0 && avg_frame_rate.denominator() > 0 {
+            f64::from(avg_frame_rate)
+        } else {
+            FALLBACK_FRAME_RATE
+        };
+        return FrameClock {
+            time_base,
+            start_pts: if start_time == i64::MIN { None } else { Some(start_time) },
+            frame_duration_ms: 1000.0 / frame_rate,
+            next_index: 0,
+            last_millis: None,
+            is_index_lost: false,
+        };
+    }
+
+    fn pts_to_millis(&self, pts: i64) -> u64 {
+        let rel_pts = pts - self.start_pts.unwrap_or(0);
+        let millis = rel_pts as f64 * 1000.0 * self.time_base.numerator() as f64
+            / self.time_base.denominator() as f64;
+        return millis.max(0.0).round() as u64;
+    }
+
+    /// timestamp to pass to the format seek, in AV_TIME_BASE (microsecond) units,
+    /// millis are relative to the stream start, like everywhere else
+    pub fn get_seek_timestamp(&self, millis: u64) -> i64 {
+        let start_micros = match self.start_pts {
+            Some(start_pts) => start_pts as f64 * 1_000_000.0 * self.time_base.numerator() as f64
+                / self.time_base.denominator() as f64,
+            None => 0.0,
+        };
+        return start_micros.round() as i64 + millis as i64 * 1000;
+    }
+
+    /// frames are numbered from the file start, so after a seek the index of the
+    /// next frame is calculated from its PTS and the stream average frame rate
+    pub fn after_seek(&mut self) {
+        self.is_index_lost = true;
+        self.last_millis = None;
+    }
+
+    /// pts is the frame best_effort_timestamp, when it is missing, time is
+    /// extrapolated from the previous frame using stream average frame rate
+    pub fn next(&mut self, pts: Option<i64>) -> FrameTime {
+        let millis = match (pts, self.last_millis) {
+            (Some(pts), _) => self.pts_to_millis(pts),
+            (None, Some(last)) => last + self.frame_duration_ms.round() as u64,
+            (None, None) => (self.next_index as f64 * self.frame_duration_ms).round() as u64,
+        };
+        if self.is_index_lost && pts.is_some() {
+            self.next_index = (millis as f64 / self.frame_duration_ms).round() as usize;
+            self.is_index_lost = false;
+        }
+        let time = FrameTime { frame_index: self.next_index, pts, millis };
+        self.next_index += 1;
+        self.last_millis = Some(millis);
+        return time;
+    }
+
+    /// the moment right after the last frame stops being displayed
+    pub fn end_time(&self) -> FrameTime {
+        let millis = match self.last_millis {
+            Some(last) => last + self.frame_duration_ms.round() as u64,
+            None => 0,
+        };
+        return FrameTime { frame_index: self.next_index, pts: None, millis };
+    }
+}
+
+/// a bound of the processed part of the video, either a time from the file start or a frame index
+#[derive(Debug, Copy, Clone)]
+pub enum RangeBound {
+    Millis(u64),
+    Frame(usize),
+}
+
+impl RangeBound {
+    fn is_before(&self, time: &FrameTime) -> bool {
+        return match self {
+            RangeBound::Millis(millis) => *millis <= time.millis,
+            RangeBound::Frame(frame_index) => *frame_index <= time.frame_index,
+        };
+    }
+}
+
+/// part of the video to process, start is inclusive, end is exclusive
+#[derive(Debug, Copy, Clone, Default)]
+pub struct TimeRange {
+    pub start: Option<RangeBound>,
+    pub end: Option<RangeBound>,
+}
+
+impl TimeRange {
+    /// where to seek before decoding, if anywhere: frame indices after a seek are only estimated
+    /// from the average frame rate, so a start given as a frame index is decoded up to from the
+    /// file start, otherwise it would land on a wrong frame of a variable frame rate video
+    pub fn get_seek_millis(&self) -> Option<u64> {
+        return match self.start {
+            Some(RangeBound::Millis(millis)) => Some(millis),
+            _ => None,
+        };
+    }
+
+    pub fn is_before_start(&self, time: &FrameTime) -> bool {
+        return match &self.start {
+            Some(start) => !start.is_before(time),
+            None => false,
+        };
+    }
+
+    pub fn is_after_end(&self, time: &FrameTime) -> bool {
+        return match &self.end {
+            Some(end) => end.is_before(time),
+            None => false,
+        };
+    }
+}
+
+/// parses "[[hours:]minutes:]seconds[.fraction]", like 1:02:03.5 or 83.5
+pub fn parse_timestamp(value: &str) -> Result<u64, String> {
+    let error = || format!("expected [[hours:]minutes:]seconds, got {}", value);
+    let parts: Vec<&str> = value.trim().split(':').collect();
+    if parts.len() > 3 {
+        return Err(error());
+    }
+    let mut millis: f64 = 0.0;
+    for (i, part) in parts.iter().enumerate() {
+        let number: f64 = part.parse().map_err(|_| error())?;
+        let is_last = i + 1 == parts.len();
+        if number < 0.0 || (!is_last && number.fract() != 0.0) {
+            return Err(error());
+        }
+        millis = millis * 60.0 + number * 1000.0;
+    }
+    return Ok(millis.round() as u64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_seconds_minutes_and_hours() {
+        assert_eq!(parse_timestamp("83.5"), Ok(83_500));
+        assert_eq!(parse_timestamp("0"), Ok(0));
+        assert_eq!(parse_timestamp("1:02"), Ok(62_000));
+        assert_eq!(parse_timestamp("1:02:03.5"), Ok(3_723_500));
+        assert_eq!(parse_timestamp(" 00:00:01.0005 "), Ok(1_001));
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        for value in ["", "abc", "1:2:3:4", "1.5:00", "-1", "1:-2", "1::2", "1,5"].iter() {
+            assert!(parse_timestamp(value).is_err(), "{:?} should be rejected", value);
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! folders of PNG/PPM screenshots, like the ones emulators save, processed as frames
+//! of a video: files are taken in the order of their names, each one is a frame
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use crate::error::{Error, Result};
+use crate::frame_time::{FrameTime, TimeRange};
+use crate::ppm_format;
+use crate::rgb_frame::RgbFrame;
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "ppm"];
+
+/// how to assign time to each image
+#[derive(Debug, Copy, Clone)]
+pub enum ImageTiming {
+    /// images were taken at a steady rate, that many per second
+    FrameRate(f64),
+    /// the last number in the file name is the time in milliseconds, like shot_000123456.png
+    FromName,
+}
+
+/// screenshots are shown for a second each when nothing else is known
+const DEFAULT_IMAGE_FRAME_RATE: f64 = 1.0;
+
+impl Default for ImageTiming {
+    fn default() -> ImageTiming {
+        return ImageTiming::FrameRate(DEFAULT_IMAGE_FRAME_RATE);
+    }
+}
+
+/// input is a directory or a glob pattern, like screenshots/*.png, rather than a video file
+pub fn is_image_sequence(input: &str) -> bool {
+    return Path::new(input).is_dir() || input.contains(|c| c == '*' || c == '?' || c == '[');
+}
+
+fn is_image_file(path: &Path) -> bool {
+    return match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()),
+        None => false,
+    };
+}
+
+/// image files of the directory or matching the glob, sorted by name
+pub fn list_images(input: &str) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = if Path::new(input).is_dir() {
+        fs::read_dir(input)
+            .map_err(Error::io(format!("Failed to read directory {}", input)))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect()
+    } else {
+        glob::glob(input)
+            .map_err(|error| Error::Input(format!("Invalid glob pattern {}: {}", input, error)))?
+            .filter_map(|path| path.ok())
+            .collect()
+    };
+    paths.retain(|path| path.is_file() && is_image_file(path));
+    paths.sort();
+    return Ok(paths);
+}
+
+/// the last group of digits in the file name without extension
+fn get_name_millis(path: &Path) -> Option<u64> {
+    let stem = path.file_stem()?.to_str()?;
+    let digits: String = stem.chars()
+        .rev()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    return digits.chars().rev().collect::<String>().parse().ok();
+}
+
+fn read_png(path: &Path) -> Result<(usize, usize, Vec<u8>)> {
+    let invalid = |error: png::DecodingError| Error::Image(format!("Invalid PNG {}: {}", path.display(), error));
+    let file = File::open(path).map_err(Error::io(format!("Failed to open {}", path.display())))?;
+    let mut decoder = png::Decoder::new(file);
+    // palette and less than 8 bit per sample get expanded to 8 bit RGB or gray
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let (info, mut reader) = decoder.read_info().map_err(invalid)?;
+    let mut buffer = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buffer).map_err(invalid)?;
+    if info.bit_depth == png::BitDepth::Sixteen {
+        // big endian, the first byte of a sample is the significant one
+        buffer = buffer.iter().step_by(2).copied().collect();
+    }
+    let samples = match info.color_type {
+        png::ColorType::RGB => 3,
+        png::ColorType::RGBA => 4,
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Indexed => return Err(Error::Image(format!("Palette of {} was not expanded", path.display()))),
+    };
+    let mut bitmap = Vec::with_capacity(info.width as usize * info.height as usize * 3);
+    for pixel in buffer.chunks(samples) {
+        if samples >= 3 {
+            bitmap.extend_from_slice(&pixel[0..3]);
+        } else {
+            bitmap.extend_from_slice(&[pixel[0], pixel[0], pixel[0]]);
+        }
+    }
+    return Ok((info.width as usize, info.height as usize, bitmap));
+}
+
+fn read_ppm(path: &Path) -> Result<(usize, usize, Vec<u8>)> {
+    let ppm = ppm_format::decode(fs::read(path).map_err(Error::io(format!("Failed to read {}", path.display())))?)?;
+    if ppm.version != "P6" || ppm.color_depth != 255 {
+        return Err(Error::Image(format!(
+            "Only binary P6 with color depth 255 is supported, {} is {} {}", path.display(), ppm.version, ppm.color_depth
+        )));
+    }
+    let bitmap_length = ppm.width * ppm.height * 3;
+    if ppm.get_bitmap().len() < bitmap_length {
+        return Err(Error::Image(format!("PPM {} is cut off", path.display())));
+    }
+    return Ok((ppm.width, ppm.height, ppm.get_bitmap()[..bitmap_length].to_vec()));
+}
+
+pub fn read_image(path: &Path, time: FrameTime) -> Result<RgbFrame> {
+    let is_png = path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.eq_ignore_ascii_case("png"))
+        .unwrap_or(false);
+    let (width, height, bitmap) = if is_png { read_png(path) } else { read_ppm(path) }?;
+    return Ok(RgbFrame::new(time, width, height, bitmap));
+}
+
+fn get_image_millis(path: &Path, frame_index: usize, timing: &ImageTiming) -> Result<u64> {
+    return match timing {
+        ImageTiming::FrameRate(frame_rate) => Ok((frame_index as f64 * 1000.0 / frame_rate).round() as u64),
+        ImageTiming::FromName => get_name_millis(path)
+            .ok_or_else(|| Error::Input(format!("No number to take the time from in the name of {}", path.display()))),
+    };
+}
+
+/// images within the range as frames, returns the moment right after the last image stops
+/// being displayed, that is the time of the image after it or one more frame duration,
+/// images that can not be read or have no time in the name are skipped, on_frame returns false to stop
+pub fn read_image_sequence(
+    input: &str,
+    timing: &ImageTiming,
+    range: &TimeRange,
+    mut on_frame: impl FnMut(RgbFrame) -> bool,
+) -> Result<FrameTime> {
+    let paths = list_images(input)?;
+    if paths.is_empty() {
+        return Err(Error::Input(format!("No PNG or PPM images found in {}", input)));
+    }
+    let first_duration = match timing {
+        ImageTiming::FrameRate(frame_rate) => 1000.0 / frame_rate,
+        ImageTiming::FromName => 1000.0 / DEFAULT_IMAGE_FRAME_RATE,
+    }.round() as u64;
+    let mut end_time = FrameTime { frame_index: 0, pts: None, millis: 0 };
+    let mut last_millis: Option<u64> = None;
+    for (frame_index, path) in paths.iter().enumerate() {
+        let millis = match get_image_millis(path, frame_index, timing) {
+            Ok(millis) => millis,
+            Err(error) => {
+                eprintln!("Skipping frame {}: {}", frame_index, error);
+                continue;
+            },
+        };
+        let time = FrameTime { frame_index, pts: None, millis };
+        if range.is_after_end(&time) {
+            break;
+        }
+        let frame_duration = match last_millis {
+            Some(last_millis) => millis.saturating_sub(last_millis),
+            None => first_duration,
+        };
+        last_millis = Some(millis);
+        end_time = FrameTime { frame_index: frame_index + 1, pts: None, millis: millis + frame_duration };
+        if range.is_before_start(&time) {
+            continue;
+        }
+        match read_image(path, time) {
+            Ok(frame) => if !on_frame(frame) {
+                break;
+            },
+            Err(error) => eprintln!("Skipping frame {}: {}", frame_index, error),
+        }
+    }
+    return Ok(end_time);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_time_from_the_last_number_of_the_name() {
+        assert_eq!(get_name_millis(Path::new("shots/shot_000123456.png")), Some(123456));
+        assert_eq!(get_name_millis(Path::new("run2_1500ms.png")), Some(1500));
+        // digits of the directory and the extension do not count
+        assert_eq!(get_name_millis(Path::new("2021/title.ppm")), None);
+        assert_eq!(get_name_millis(Path::new("18446744073709551616.png")), None);
+    }
+
+    #[test]
+    fn names_without_number_are_an_error() {
+        assert!(get_image_millis(Path::new("title.png"), 0, &ImageTiming::FromName).is_err());
+        assert_eq!(get_image_millis(Path::new("title.png"), 3, &ImageTiming::FrameRate(2.0)).unwrap(), 1500);
+    }
+
+    #[test]
+    fn recognizes_image_files_by_extension() {
+        assert!(is_image_file(Path::new("shot.PNG")));
+        assert!(is_image_file(Path::new("shot.ppm")));
+        assert!(!is_image_file(Path::new("shot.jpg")));
+        assert!(!is_image_file(Path::new("png")));
+    }
+}
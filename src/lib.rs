@@ -0,0 +1,176 @@
+//! finds frames of a video in which hardsub text changed and recognizes the text by
+//! comparing letters to glyphs of the font the subtitles were rendered with
+//!
+//! the entry points are detect_text_changes() for frames from any source, spawn_decoding()
+//! for video files, image sequences and pipes, and ocr_frame() with a FontData::from_file()
+//! font, run_cli() is the whole detect-still-frames binary, the items re-exported here are
+//! the API, the modules themselves are internal
+
+mod benchmark;
+mod blink_mask;
+mod change_classifier;
+mod charset;
+mod cjk_segmentation;
+mod detect_still_frames;
+mod error;
+mod match_letter_to_font;
+mod ocr_out_from_image;
+mod pipe_input;
+mod pixel_utils;
+mod ppm_format;
+mod font_data;
+mod font_identification;
+mod font_search;
+mod font_size;
+mod frame_blocks;
+mod frame_pipeline;
+mod frame_region;
+mod frame_time;
+mod image_sequence;
+mod live_ocr;
+mod rel_matrix;
+mod rgb_frame;
+mod scene_detection;
+mod srt_format;
+mod subtitle_events;
+mod text_region_heatmap;
+mod timeline;
+mod video_decoding;
+mod video_to_srt;
+
+mod cli;
+
+pub use crate::change_classifier::{ChangeKind, ClassifierParams};
+pub use crate::charset::Charset;
+pub use crate::cli::run_cli;
+pub use crate::cjk_segmentation::Segmentation;
+pub use crate::detect_still_frames::{for_each_frame_change, DetectionParams, DetectionSummary, FrameChange};
+pub use crate::error::{Error, Result};
+pub use crate::font_data::FontData;
+pub use crate::font_search::{find_font, get_system_font_dirs, FontLocation};
+pub use crate::frame_region::{FrameRegion, IgnoreMask};
+pub use crate::frame_time::{FrameTime, RangeBound, TimeRange};
+pub use crate::image_sequence::ImageTiming;
+pub use crate::pipe_input::{PipeFormat, PipeInput};
+pub use crate::pixel_utils::Point;
+pub use crate::rel_matrix::Bounds;
+pub use crate::rgb_frame::RgbFrame;
+pub use crate::scene_detection::{SceneChange, SceneChangeKind};
+pub use crate::subtitle_events::{EventEnd, EventSegmenter, SubtitleEvent};
+pub use crate::video_decoding::{spawn_decoding, InputParams};
+
+use std::cell::Cell;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread::{self, JoinHandle};
+use crate::frame_pipeline::process_frames;
+use crate::ocr_out_from_image::SubsOcrFrame;
+
+/// events waiting to be taken by the caller, detection pauses when the queue is full
+const TEXT_CHANGES_QUEUE: usize = 16;
+
+/// tightly packed R,G,B bytes, row by row
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub bitmap: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, bitmap: Vec<u8>) -> Result<Image> {
+        if bitmap.len() != width * height * 3 {
+            return Err(Error::Image(format!(
+                "Image {}x{} needs {} bitmap bytes, got {}", width, height, width * height * 3, bitmap.len()
+            )));
+        }
+        return Ok(Image { width, height, bitmap });
+    }
+}
+
+/// a frame where hardsub text appeared, changed or got typed till the end
+#[derive(Debug, Clone)]
+pub struct TextChangeEvent {
+    pub time: FrameTime,
+    /// set when the text was typed letter by letter, see DetectionParams::settle_frames
+    pub animation_start: Option<FrameTime>,
+    pub animation_end: Option<FrameTime>,
+    /// area of the changed pixels
+    pub changed_bounds: Option<Bounds>,
+    /// the whole frame, to take colors of the letters from
+    pub full: Image,
+    /// only the pixels that changed, everything else is black
+    pub changed: Image,
+}
+
+impl TextChangeEvent {
+    fn from_change(change: FrameChange) -> TextChangeEvent {
+        return TextChangeEvent {
+            time: change.time,
+            animation_start: change.animation_start,
+            animation_end: change.animation_end,
+            changed_bounds: change.changed_bounds,
+            full: Image { width: change.width, height: change.height, bitmap: change.full_bitmap },
+            changed: Image { width: change.width, height: change.height, bitmap: change.text_only_bitmap },
+        };
+    }
+}
+
+/// iterator over the events of detect_text_changes(), the detection runs ahead on its own thread
+pub struct TextChanges {
+    receiver: Receiver<TextChangeEvent>,
+    detection: Option<JoinHandle<()>>,
+}
+
+impl Iterator for TextChanges {
+    type Item = TextChangeEvent;
+
+    fn next(&mut self) -> Option<TextChangeEvent> {
+        if let Ok(event) = self.receiver.recv() {
+            return Some(event);
+        }
+        if let Some(detection) = self.detection.take() {
+            if let Err(panic) = detection.join() {
+                std::panic::resume_unwind(panic);
+            }
+        }
+        return None;
+    }
+}
+
+/// text changes among the frames, which can come from anywhere, like the receiver of
+/// spawn_decoding() or frames captured from a game, frame times are passed through as is
+pub fn detect_text_changes<I>(frames: I, params: &DetectionParams) -> TextChanges
+where
+    I: IntoIterator<Item = RgbFrame>,
+    I::IntoIter: Send + 'static,
+{
+    let (event_sender, event_receiver) = sync_channel(TEXT_CHANGES_QUEUE);
+    let frames = frames.into_iter();
+    let params = params.clone();
+    let detection = thread::spawn(move || {
+        // dropped iterator means nobody needs the rest of the frames
+        let is_receiver_gone = Cell::new(false);
+        let frames = frames.take_while(|_| !is_receiver_gone.get());
+        process_frames(frames, &params, &mut |change| {
+            if change.is_text_change && event_sender.send(TextChangeEvent::from_change(change)).is_err() {
+                is_receiver_gone.set(true);
+            }
+        });
+    });
+    return TextChanges { receiver: event_receiver, detection: Some(detection) };
+}
+
+/// recognized text of a frame, top to bottom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OcrResult {
+    pub lines: Vec<String>,
+}
+
+/// recognize hardsub text in a frame, letters are looked for among the changed pixels
+/// and their colors are taken from the full frame, both have to be of the same size
+pub fn ocr_frame(full: &Image, changed: &Image, font: &FontData) -> Result<OcrResult> {
+    let full_ppm = ppm_format::from_bitmap(full.width, full.height, &full.bitmap[..]);
+    let text_ppm = ppm_format::from_bitmap(changed.width, changed.height, &changed.bitmap[..]);
+    let lines = ocr_out_from_image::ocr_frame(&SubsOcrFrame::new(full_ppm, text_ppm)?, font, &FrameRegion::default());
+    return Ok(OcrResult { lines });
+}
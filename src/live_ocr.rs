@@ -0,0 +1,85 @@
+//! follows a capture stream, like a game being played, rather than a finished recording:
+//! frames go through the detector as soon as they arrive and recognized text is printed
+//! right when it settles, the font and its size are given upfront rather than guessed
+//! from the first texts, and frames are compared on one thread with a short queue,
+//! so that neither memory nor latency grows with the run time or the core count
+
+use crate::detect_still_frames::{for_each_frame_change, DetectionParams};
+use crate::error::{Error, Result};
+use crate::font_data::FontData;
+use crate::srt_format::format_timestamp;
+use crate::subtitle_events::{EventEnd, EventSegmenter};
+use crate::video_decoding::InputParams;
+use crate::video_to_srt::{make_ocr_frame, ocr_lines};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+
+/// frames decoded ahead of the analysis, a capture delivers them in real time anyway
+const LIVE_DECODE_QUEUE: usize = 2;
+
+struct LiveOutput<'a> {
+    log: Option<(File, &'a str)>,
+    /// only the text on screen is kept, older texts are already printed
+    shown_lines: Option<Vec<String>>,
+}
+
+impl LiveOutput<'_> {
+    fn show(&mut self, start_millis: u64, lines: Vec<String>) -> Result<()> {
+        if self.shown_lines.as_ref() == Some(&lines) {
+            return Ok(());
+        }
+        let text: String = lines.iter()
+            .map(|line| format!("[{}] {}\n", format_timestamp(start_millis), line))
+            .collect();
+        print!("{}", text);
+        std::io::stdout().flush().map_err(Error::io("Failed to write to stdout".to_string()))?;
+        if let Some((log, log_path)) = &mut self.log {
+            log.write_all(text.as_bytes())
+                .and_then(|_| log.flush())
+                .map_err(Error::io(format!("Failed to write {}", log_path)))?;
+        }
+        self.shown_lines = Some(lines);
+        Ok(())
+    }
+}
+
+/// prints each recognized text with its time to stdout and, if given, appends it to the log file,
+/// same text detected again before it was cleared is not repeated, params.threads is ignored
+pub fn live_ocr(
+    path: &str,
+    log_path: Option<&str>,
+    font_data: &FontData,
+    params: &DetectionParams,
+) -> Result<()> {
+    let params = DetectionParams {
+        threads: 1,
+        input: InputParams { decode_queue: Some(LIVE_DECODE_QUEUE), ..params.input },
+        ..params.clone()
+    };
+    let log = match log_path {
+        Some(log_path) => Some((
+            OpenOptions::new().create(true).append(true).open(log_path)
+                .map_err(Error::io(format!("Failed to open {}", log_path)))?,
+            log_path,
+        )),
+        None => None,
+    };
+    let mut output = LiveOutput { log, shown_lines: None };
+    let mut segmenter = EventSegmenter::new();
+
+    for_each_frame_change(path, &params, |change| {
+        let mut written = Ok(());
+        let closed = segmenter.feed(&change, |change| {
+            let lines = ocr_lines(&make_ocr_frame(change)?, font_data, &params.region)?;
+            written = output.show(change.animation_start.unwrap_or(change.time).millis, lines);
+            Some(())
+        });
+        if let Some(event) = closed {
+            if event.end_reason == EventEnd::Cleared {
+                output.shown_lines = None;
+            }
+        }
+        written
+    })?;
+    Ok(())
+}
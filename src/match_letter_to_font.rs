@@ -3,14 +3,13 @@
 //! an OCR if you will
 
 use crate::pixel_utils::{Color, Pixel, Point};
-use crate::ppm_format;
-use ab_glyph::{point, Font, FontRef, Glyph};
-use std::cmp::{max, min, Ordering};
+use std::cmp::{max, Ordering};
 use std::collections::BinaryHeap;
-use std::fs::File;
-use std::io::Write;
-use crate::rel_matrix::{make_rel_bitmap, RelMatrix, PixelCoverage};
-use crate::font_data::{CHAR_OPTIONS, FontData};
+use crate::rel_matrix::{make_rel_bitmap, RelMatrix, PixelCoverage, Signature};
+use crate::font_data::{FontData, LARGE_CHARSET};
+
+/// chars of a large charset with the closest signatures that get compared pixel by pixel
+const SHORTLISTED_CHARS: usize = 40;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct CharMatch {
@@ -32,47 +31,6 @@ impl PartialOrd for CharMatch {
     }
 }
 
-fn draw_debug(img_bitmap: &[Vec<f32>], font_bitmap: &[Vec<f32>], suffix: String) {
-    let img_width = img_bitmap.len();
-    let img_height = img_bitmap[0].len();
-    let font_width = font_bitmap.len();
-    let font_height = font_bitmap[0].len();
-
-    let width = img_width + font_width;
-    let first_row_height = max(img_height, font_height);
-    let height = first_row_height + font_height;
-    let ppm_header = ppm_format::make_header(width, height);
-
-    let mut file = File::create(format!("out/ocr_debug/huj{}.ppm", suffix)).unwrap();
-    file.write_all(ppm_header.as_bytes()).unwrap();
-    for y in 0..height {
-        for x in 0..width {
-            let mut color = Color::BLACK;
-            if y < first_row_height {
-                if x < img_width {
-                    if y < img_height {
-                        let c = img_bitmap[x][y];
-                        color = coverage_to_color(c);
-                    }
-                } else {
-                    let rel_x = x - img_width;
-                    if y < font_height {
-                        let c = font_bitmap[rel_x][y];
-                        color = coverage_to_color(c);
-                    }
-                }
-            } else {
-                if x < font_width {
-                    let rel_y = y - first_row_height;
-                    let c = font_bitmap[x][rel_y];
-                    color = coverage_to_color(c);
-                }
-            }
-            file.write(&color.to_vector());
-        }
-    }
-}
-
 /// if same pixel on both font bitmap and image bitmap is of completely same lightness, result is 1.0
 /// if pixel is completely black on one and completely white on the other, result is 0.0
 fn get_pixel_score(x: usize, y: usize, c: f32, img_bitmap: &[Vec<f32>]) -> f32 {
@@ -180,8 +138,6 @@ fn match_bitmap_to_char(
     img_bitmap: &[Vec<f32>],
     char: char,
     font_data: &FontData,
-    is_expected: bool,
-    suffix: String,
 ) -> CharMatch {
     let mut matches = BinaryHeap::new();
     for (i, font_matrix) in font_data.get_bitmaps(char).iter().enumerate() {
@@ -196,15 +152,6 @@ fn match_bitmap_to_char(
             font_shift_index: i,
         };
 
-        if is_expected {
-            // draw_debug(
-            //     img_bitmap, &font_matrix.bitmap,
-            //     format!(
-            //         "_{}_{}_{}_{}", suffix, char, i,
-            //         match_option.match_score / 100000
-            //     ),
-            // );
-        }
         matches.push(match_option);
     }
     return matches.pop().unwrap();
@@ -214,11 +161,6 @@ fn color_to_coverage(color: &Color) -> f32 {
     return (color.r as f32 + color.g as f32 + color.b as f32) / (255.0 * 3.0);
 }
 
-fn coverage_to_color(coverage: f32) -> Color {
-    let lightness = (255.0 * coverage) as u8;
-    return Color { r: lightness, g: lightness, b: lightness };
-}
-
 pub fn make_rel_bitmap_from_image(letter_pixels: &[Pixel]) -> RelMatrix {
     return make_rel_bitmap(
         letter_pixels
@@ -232,42 +174,47 @@ pub fn make_rel_bitmap_from_image(letter_pixels: &[Pixel]) -> RelMatrix {
     );
 }
 
+/// chars the letter is compared to: all of them, or, for a large charset,
+/// the ones of roughly the same shape, like kanji with the same radicals
+fn get_char_options(rel_bitmap: &[Vec<f32>], font_data: &FontData) -> Vec<char> {
+    if font_data.chars.len() <= LARGE_CHARSET {
+        return font_data.chars.clone();
+    }
+    let signature = Signature::new(rel_bitmap);
+    let mut distances: Vec<(f32, char)> = font_data.chars.iter()
+        .map(|char| (font_data.char_to_signature[char].get_distance(&signature), *char))
+        .collect();
+    distances.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+    return distances.into_iter().take(SHORTLISTED_CHARS).map(|(_, char)| char).collect();
+}
+
+/// how well the letter fits the char of the font it fits best, in range [0..1]
+pub fn get_best_match_score(rel_bitmap: &[Vec<f32>], font_data: &FontData) -> f64 {
+    return get_char_options(rel_bitmap, font_data).iter()
+        .map(|char| match_bitmap_to_char(rel_bitmap, *char, font_data).match_score)
+        .max()
+        .unwrap_or(0) as f64 / 10000000.0;
+}
+
 pub fn match_letter_to_font(
     rel_bitmap: &Vec<Vec<f32>>,
     font_data: &FontData,
-    index: usize,
 ) -> Vec<CharMatch> {
-    let expected = [
-        'T', 'h', 'e', 'r', 'e', 'a', 'r', 'e', 'm','a','n','y','t','h','e','o','r','i','e','s','a','b','o','u','t',
-        't','h','e','d','i','v','i','s','i','o','n','b','e','t','w','e','e','n','L','a','t','e','M','o','d','e','r','n',
-        'P','e','r','i','o','d','a','n','d','C','o','n','t','e','m','p','o','r','a','r','H','i','s','t','o','r',
-        'O','n','e','t','h','e','o','r','y','m','a','r','k','s','t','h','e','b','e','g','i','n','n','i','n','g',
-        'o','f','t','h','e','C','o','n','t','e','m','p','o','r','a','r','H','i','s','t','o','r','w','i','t','h',
-        't','h','e','b','i','r','h','o','f','t','h','e','n','e','w','m','o','d','e','l','t','s','u','r','u','g','i',
-    ];
-
-    let mut matches = BinaryHeap::new();
-    for char in &CHAR_OPTIONS {
-        let is_expected = index < expected.len() && expected[index] == *char;
-        let matched = match_bitmap_to_char(rel_bitmap, *char, font_data, is_expected, index.to_string());
-        if is_expected {
-            println!("expect match #{}: {:?}", index, matched);
-        }
-        matches.push(matched);
-    }
+    let mut matches: BinaryHeap<CharMatch> = get_char_options(rel_bitmap, font_data).iter()
+        .map(|char| match_bitmap_to_char(rel_bitmap, *char, font_data))
+        .collect();
     let mut extra_matches = Vec::new();
     // you also think it should be for arbitrary number of letters?
     if matches.peek().unwrap().match_score < 8000000 {
         for bad_match in &matches {
             if bad_match.font_area_score >= 8000000 {
-                println!("ololo index {} partial match {}", index, bad_match.char);
                 let options = font_data.get_bitmaps(bad_match.char.chars().next().unwrap());
                 let cutout_x = options[bad_match.font_shift_index].bitmap.len();
                 if cutout_x < rel_bitmap.len() {
                     let hardsub_proportion = cutout_x as f32 / rel_bitmap.len() as f32;
                     let remaining_bitmap = &rel_bitmap[cutout_x ..];
-                    for char in &CHAR_OPTIONS {
-                        let remainder_match = match_bitmap_to_char(remaining_bitmap, *char, font_data, false, format!("{}.{}", index, bad_match.char));
+                    for char in &get_char_options(remaining_bitmap, font_data) {
+                        let remainder_match = match_bitmap_to_char(remaining_bitmap, *char, font_data);
                         extra_matches.push(CharMatch {
                             char: format!("{}{}", bad_match.char, remainder_match.char),
                             font_area_score: (
@@ -2,34 +2,31 @@ use crate::match_letter_to_font::{match_letter_to_font, CharMatch, make_rel_bitm
 use crate::pixel_utils::{get_surrounding, Color, Pixel, Point};
 use crate::ppm_format;
 use crate::ppm_format::PpmData;
-use ab_glyph::FontRef;
 use std::fs;
 use std::fs::File;
 use std::io::prelude::*;
-use std::io::Read;
 use std::collections::BinaryHeap;
-use std::cmp::{Ordering, max, min};
-use crate::rel_matrix::{Bounds, RelMatrix};
-use crate::font_data::FontData;
-
-fn read_file(suffix: &str) -> PpmData {
-    let frame_name = "frame15";
-    let path = format!("out/change_frames/{}{}.ppm", frame_name, suffix);
-    let metadata = fs::metadata(&path).unwrap();
-    let mut file = File::open(path).unwrap();
-    let file_size = metadata.len() as usize;
-    let mut raw_bytes = vec![0 as u8; file_size];
-    file.read(&mut raw_bytes).unwrap();
+use std::cmp::{max, min};
+use crate::rel_matrix::{resize_bitmap, Bounds, RelMatrix};
+use crate::cjk_segmentation::{segment_cell_lines, Segmentation};
+use crate::font_data::{FontData, DEFAULT_SCALE};
+use crate::font_identification::FontSelection;
+use crate::frame_region::FrameRegion;
+use crate::error::{Error, Result};
+
+fn read_file(frames_dir: &str, frame_name: &str, suffix: &str) -> Result<PpmData<'static>> {
+    let path = format!("{}/{}{}.ppm", frames_dir, frame_name, suffix);
+    let raw_bytes = fs::read(&path).map_err(Error::io(format!("Failed to read frame {}", path)))?;
 
     return ppm_format::decode(raw_bytes);
 }
 
-struct SubsOcrFrame {
-    full_ppm: PpmData,
-    text_ppm: PpmData,
+pub struct SubsOcrFrame<'a> {
+    full_ppm: PpmData<'a>,
+    text_ppm: PpmData<'a>,
 }
 
-impl SubsOcrFrame {
+impl<'a> SubsOcrFrame<'a> {
     fn get_width(&self) -> usize {
         return self.full_ppm.width;
     }
@@ -51,27 +48,39 @@ impl SubsOcrFrame {
         return self.full_ppm.get_pixel(&point);
     }
 
-    fn load() -> SubsOcrFrame {
+    fn load(frames_dir: &str, frame_name: &str) -> Result<SubsOcrFrame<'static>> {
         // TODO; wrong naming, it's not old/new, it's full/text-only
-        let full_ppm = read_file("_old");
-        let text_ppm = read_file("_new");
+        let full_ppm = read_file(frames_dir, frame_name, "_old")?;
+        let text_ppm = read_file(frames_dir, frame_name, "_new")?;
 
+        return SubsOcrFrame::new(full_ppm, text_ppm);
+    }
+
+    pub fn new(full_ppm: PpmData<'a>, text_ppm: PpmData<'a>) -> Result<SubsOcrFrame<'a>> {
         let ocr_frame = SubsOcrFrame { full_ppm, text_ppm };
         if ocr_frame.get_bitmap_length() != ocr_frame.full_ppm.get_bitmap().len() {
-            panic!(
-                "Ololo pizdec unsupported pixel format {} -> {} != {}",
+            return Err(Error::Image(format!(
+                "Unsupported pixel format, color depth {}: expected {} bitmap bytes, got {}",
                 ocr_frame.full_ppm.color_depth,
                 ocr_frame.get_bitmap_length(),
                 ocr_frame.full_ppm.get_bitmap().len()
-            );
+            )));
+        }
+        if ocr_frame.text_ppm.get_bitmap().len() != ocr_frame.full_ppm.get_bitmap().len() {
+            return Err(Error::FrameSize {
+                expected: (ocr_frame.full_ppm.width, ocr_frame.full_ppm.height),
+                got: (ocr_frame.text_ppm.width, ocr_frame.text_ppm.height),
+            });
         }
 
-        return ocr_frame;
+        return Ok(ocr_frame);
     }
 }
 
 struct OcrProcess<'a> {
-    ocr_frame: &'a SubsOcrFrame,
+    ocr_frame: &'a SubsOcrFrame<'a>,
+    /// letters are searched only inside of it
+    region: &'a FrameRegion,
     /// x-to-y-to-bool
     checked_points: Vec<Vec<bool>>,
     matched_points: Vec<Point>,
@@ -79,13 +88,14 @@ struct OcrProcess<'a> {
 }
 
 impl OcrProcess<'_> {
-    fn init(ocr_frame: &SubsOcrFrame) -> OcrProcess {
+    fn init<'a>(ocr_frame: &'a SubsOcrFrame<'a>, region: &'a FrameRegion) -> OcrProcess<'a> {
         let checked_points = vec![vec![false; ocr_frame.get_height()]; ocr_frame.get_width()];
         let matched_points = Vec::new();
         let output_bitmap = vec![0; ocr_frame.full_ppm.get_bitmap().len()];
 
         return OcrProcess {
             ocr_frame,
+            region,
             checked_points,
             matched_points,
             output_bitmap,
@@ -138,7 +148,10 @@ impl OcrProcess<'_> {
             let base_point = pick_points.pop().unwrap();
             for next_point in self.check_surrounding(base_point) {
                 let pixel = self.ocr_frame.get_pixel(&next_point);
-                if pixel.is_closely_black() {
+                if !self.region.contains(&next_point) {
+                    // letter sticks out of the text region, likely not a letter at all
+                    non_black_border = true;
+                } else if pixel.is_closely_black() {
                     // black outline of the letters
                 } else if pixel.is_somewhat_white() || pixel.is_greyish() {
                     self.keep_pixel(next_point);
@@ -160,8 +173,8 @@ impl OcrProcess<'_> {
         return &self.matched_points[matched_points_start..];
     }
 
-    fn save_file(&self, name: &str) -> std::result::Result<(), std::io::Error> {
-        let mut file = File::create(format!("out/change_frames/{}.ppm", name))?;
+    fn save_file(&self, out_dir: &str, name: &str) -> std::result::Result<(), std::io::Error> {
+        let mut file = File::create(format!("{}/{}.ppm", out_dir, name))?;
         let ppm_header = ppm_format::make_header(
             self.ocr_frame.get_width(),
             self.ocr_frame.get_height(),
@@ -172,57 +185,49 @@ impl OcrProcess<'_> {
     }
 }
 
-fn get_font_data() -> FontData {
-    let font_bytes = include_bytes!("../arial.ttf");
-    let font_ref = FontRef::try_from_slice(font_bytes).unwrap();
-    return FontData::init(&font_ref);
-}
-
 struct OcredChar {
     bounds: Bounds,
     char_matches: Vec<CharMatch>,
 }
 
-fn group_chars_by_line(ocred_chars: Vec<OcredChar>) -> Vec<Vec<OcredChar>> {
-    let mut lines: Vec<Vec<OcredChar>> = Vec::new();
-    for ocred_char in ocred_chars {
-        let mut target_line: Option<usize> = None;
-        for i in 0..lines.len() {
-            let start_y = lines[i][0].bounds.start.y;
-            if ocred_char.bounds.start.y < start_y + 20 {
-                target_line = Some(i);
-                break;
-            }
-        }
-        let line_number: usize = match target_line {
-            Some(i) => i,
-            None => {
-                let mut new_line = Vec::new();
-                lines.push(new_line);
-                lines.len() - 1
+/// gap between letters that makes a space at the default font size
+const SPACE_X_THRESHOLD: f32 = 5.0;
+
+/// how much lower than the top of its line a letter can start: lowercase letters start lower than
+/// capitals by less than a typical letter height, while the next line starts lower by more than that
+fn get_line_y_threshold(rel_bitmaps: &[RelMatrix]) -> i64 {
+    let mut heights: Vec<usize> = rel_bitmaps.iter().map(|rel_bitmap| rel_bitmap.bounds.get_height()).collect();
+    heights.sort_unstable();
+    return heights.get(heights.len() / 2).map_or(0, |height| *height as i64);
+}
+
+/// lines top to bottom and letters left to right inside of a line, a letter belongs to the line above it if
+/// it starts less than line_threshold lower than the top of the line, or above the lowest
+/// bottom of its letters, like a comma does, the next line always starts below that
+fn sort_in_reading_order(mut rel_bitmaps: Vec<RelMatrix>, line_threshold: i64) -> Vec<Vec<RelMatrix>> {
+    rel_bitmaps.sort_by_key(|rel_bitmap| rel_bitmap.bounds.start.y);
+    let mut lines: Vec<Vec<RelMatrix>> = Vec::new();
+    let mut line_bottom = 0;
+    for rel_bitmap in rel_bitmaps {
+        let start_y = rel_bitmap.bounds.start.y;
+        match lines.last_mut() {
+            Some(line) if start_y < line[0].bounds.start.y + line_threshold || start_y <= line_bottom => {
+                line_bottom = max(line_bottom, rel_bitmap.bounds.end.y);
+                line.push(rel_bitmap);
             },
-        };
-        lines[line_number].push(ocred_char);
+            _ => {
+                line_bottom = rel_bitmap.bounds.end.y;
+                lines.push(vec![rel_bitmap]);
+            },
+        }
     }
-    for i in 0..lines.len() {
-        lines[i].sort_by(|a, b| a.bounds.start.x.cmp(&b.bounds.start.x));
+    for line in lines.iter_mut() {
+        line.sort_by_key(|rel_bitmap| rel_bitmap.bounds.start.x);
     }
     return lines;
 }
 
-const SAME_LINE_Y_THRESHOLD: u32 = 15;
-
-fn cmp_letters_order(a: &RelMatrix, b: &RelMatrix) -> Ordering {
-    return if a.bounds.start.y > b.bounds.start.y + SAME_LINE_Y_THRESHOLD as i64 {
-        Ordering::Greater // a is below b
-    } else if b.bounds.start.y > a.bounds.start.y + SAME_LINE_Y_THRESHOLD as i64 {
-        Ordering::Less // a is above b
-    } else {
-        a.bounds.start.x.cmp(&b.bounds.start.x)
-    }
-}
-
-fn are_parts_of_same_char(prev_item: &RelMatrix, current_item: &RelMatrix) -> bool {
+fn are_parts_of_same_char(prev_item: &RelMatrix, current_item: &RelMatrix, line_threshold: i64) -> bool {
     let x_overlap_start = max(prev_item.bounds.start.x, current_item.bounds.start.x);
     let x_overlap_end = min(prev_item.bounds.end.x, current_item.bounds.end.x);
     let width = min(
@@ -235,10 +240,47 @@ fn are_parts_of_same_char(prev_item: &RelMatrix, current_item: &RelMatrix) -> bo
 
     let y_start_offset = (prev_item.bounds.start.y - current_item.bounds.start.y).abs();
 
-    return x_overlap_rel > 0.5 && y_start_offset < SAME_LINE_Y_THRESHOLD as i64;
+    return x_overlap_rel > 0.5 && y_start_offset < line_threshold;
+}
+
+/// diacritics and dots, like the two dots of "ï" or the dot of "!", are small pieces right above
+/// or below a bigger one, they can be wider than a thin letter and not overlap it at all
+fn is_mark_of(mark: &RelMatrix, base: &RelMatrix, line_threshold: i64) -> bool {
+    let gap = if mark.bounds.end.y < base.bounds.start.y {
+        base.bounds.start.y - mark.bounds.end.y
+    } else if mark.bounds.start.y > base.bounds.end.y {
+        mark.bounds.start.y - base.bounds.end.y
+    } else {
+        return false;
+    };
+    let reach = mark.bounds.get_width() as i64 / 2 + 1;
+    let center_x = (mark.bounds.start.x + mark.bounds.end.x) / 2;
+    return gap < line_threshold / 2
+        && mark.bounds.get_height() * 2 < base.bounds.get_height()
+        && center_x >= base.bounds.start.x - reach
+        && center_x <= base.bounds.end.x + reach;
+}
+
+/// merges every mark into the piece it belongs to, the merged char takes the place of that piece
+fn attach_marks(rel_bitmaps: Vec<RelMatrix>, line_threshold: i64) -> Vec<RelMatrix> {
+    let base_indexes: Vec<Option<usize>> = (0..rel_bitmaps.len())
+        .map(|i| (0..rel_bitmaps.len()).find(|j| *j != i && is_mark_of(&rel_bitmaps[i], &rel_bitmaps[*j], line_threshold)))
+        .collect();
+    let mut pieces: Vec<Option<RelMatrix>> = rel_bitmaps.into_iter().map(Some).collect();
+    for (i, base_index) in base_indexes.iter().enumerate() {
+        if let Some(j) = base_index {
+            // a mark of a mark stays on its own
+            if base_indexes[*j].is_none() {
+                let mark = pieces[i].take().unwrap();
+                let base = pieces[*j].take().unwrap();
+                pieces[*j] = Some(merge_char_parts(&base, &mark));
+            }
+        }
+    }
+    return pieces.into_iter().flatten().collect();
 }
 
-fn merge_char_parts(prev_item: &RelMatrix, current_item: &RelMatrix) -> RelMatrix {
+pub(crate) fn merge_char_parts(prev_item: &RelMatrix, current_item: &RelMatrix) -> RelMatrix {
     let bounds = Bounds {
         start: Point {
             x: min(
@@ -280,8 +322,10 @@ fn merge_char_parts(prev_item: &RelMatrix, current_item: &RelMatrix) -> RelMatri
 }
 
 /// dots on "i"s are extracted as separate characters, but they can be easily
-/// deducted as their x position clashes with position of the stick part
-fn dot_the_is(mut rel_bitmaps: Vec<RelMatrix>) -> Vec<RelMatrix> {
+/// deducted as their x position clashes with position of the stick part, takes pieces
+/// of one line, so that a period or a comma is not merged into a letter of the next line
+fn dot_the_is(rel_bitmaps: Vec<RelMatrix>, line_threshold: i64) -> Vec<RelMatrix> {
+    let mut rel_bitmaps = attach_marks(rel_bitmaps, line_threshold);
     let mut dotted = Vec::new();
     let mut current_item_opt = rel_bitmaps.pop();
     while current_item_opt.is_some() {
@@ -289,7 +333,7 @@ fn dot_the_is(mut rel_bitmaps: Vec<RelMatrix>) -> Vec<RelMatrix> {
         let prev_item_opt = rel_bitmaps.pop();
         if prev_item_opt.is_some() {
             let prev_item = prev_item_opt.unwrap();
-            if are_parts_of_same_char(&prev_item, &current_item) {
+            if are_parts_of_same_char(&prev_item, &current_item, line_threshold) {
                 current_item_opt = Some(
                     merge_char_parts(&prev_item, &current_item)
                 );
@@ -306,20 +350,16 @@ fn dot_the_is(mut rel_bitmaps: Vec<RelMatrix>) -> Vec<RelMatrix> {
     return dotted;
 }
 
-/// run through every white-ish pixel in the image, find the borders of the
-/// symbol it belongs to, (like magic stick in photoshop), then compare
-/// resulting bitmap to every character in the Sans-serif font
-pub fn ocr_out_from_image<'a>() {
-    let ocr_frame = SubsOcrFrame::load();
-    let mut process = OcrProcess::init(&ocr_frame);
-    let font_data = get_font_data();
-
+/// run through every white-ish pixel in the image and find the borders of the
+/// symbol it belongs to, (like magic stick in photoshop), top to bottom
+fn find_pieces(process: &mut OcrProcess) -> Vec<RelMatrix> {
+    let ocr_frame = process.ocr_frame;
     let mut rel_bitmaps: Vec<RelMatrix> = Vec::new();
     for y in 0..ocr_frame.get_height() as i64 {
         for x in 0..ocr_frame.get_width() as i64 {
             let point = Point { x, y };
             let pixel = ocr_frame.text_ppm.get_pixel(&point);
-            if pixel != Color::BLACK {
+            if pixel != Color::BLACK && process.region.contains(&point) {
                 // this pixel had a significant change in
                 // the frame, likely a part of the hardsub
                 let letter_pixels: Vec<Pixel> = process
@@ -337,41 +377,178 @@ pub fn ocr_out_from_image<'a>() {
             }
         }
     }
+    return rel_bitmaps;
+}
+
+/// connected pieces merged into letters, lines top to bottom, the only place letters are grouped into lines
+fn segment_letters(process: &mut OcrProcess) -> Vec<Vec<RelMatrix>> {
+    let rel_bitmaps = find_pieces(process);
+    let line_threshold = get_line_y_threshold(&rel_bitmaps);
+    return sort_in_reading_order(rel_bitmaps, line_threshold)
+        .into_iter()
+        .map(|line| dot_the_is(line, line_threshold))
+        .collect();
+}
 
-    println!("points picked: {}", process.matched_points.len());
-    process.save_file("frame15_white_only").unwrap();
+/// connected white pieces of the frame as they are, strokes of a kanji are separate pieces
+pub fn segment_pieces(ocr_frame: &SubsOcrFrame, region: &FrameRegion) -> Vec<RelMatrix> {
+    return find_pieces(&mut OcrProcess::init(ocr_frame, region));
+}
 
-    rel_bitmaps.sort_by(cmp_letters_order);
-    rel_bitmaps = dot_the_is(rel_bitmaps);
-    let mut ocred_chars: Vec<OcredChar> = Vec::new();
-    for rel_bitmap in rel_bitmaps {
-        let char_matches = match_letter_to_font(&rel_bitmap.bitmap, &font_data, ocred_chars.len());
+/// bitmaps of the letters found in the frame the way the font segments them, not
+/// matched to any font yet, ruby is left out in the Japanese mode, lines in reading order
+pub fn segment_glyphs(ocr_frame: &SubsOcrFrame, region: &FrameRegion, font_data: &FontData) -> Vec<Vec<RelMatrix>> {
+    let mut process = OcrProcess::init(ocr_frame, region);
+    return match font_data.segmentation {
+        Segmentation::Letters => segment_letters(&mut process),
+        Segmentation::FullWidthCells { .. } => segment_cell_lines(find_pieces(&mut process), font_data, true)
+            .into_iter()
+            .map(|line| line.cells)
+            .collect(),
+    };
+}
 
-        let next_best = &char_matches[0];
-        let comment = if next_best.match_score < 8000000 { "huj" } else { "" };
-        println!("actual match #{}: {:?} {}", ocred_chars.len(), next_best, comment);
+/// small pieces next to each other at the same height, like the strokes of a double quote,
+/// which does not fit are_parts_of_same_char(), since its strokes do not overlap by x
+fn are_strokes_side_by_side(prev_item: &RelMatrix, current_item: &RelMatrix, letter_height: i64, max_gap: i64) -> bool {
+    let is_small = |item: &RelMatrix| item.bounds.get_height() as i64 * 2 < letter_height;
+    return is_small(prev_item)
+        && is_small(current_item)
+        && current_item.bounds.start.x - prev_item.bounds.end.x <= max_gap
+        && current_item.bounds.start.y <= prev_item.bounds.end.y
+        && prev_item.bounds.start.y <= current_item.bounds.end.y;
+}
 
+/// compare every letter of a line to the chars of the font, strokes side by side are
+/// compared merged too and taken as one char if that matches no worse than each of them
+/// alone, so a double quote is found when the charset has it and two apostrophes otherwise
+fn recognize_line(line: Vec<RelMatrix>, font_data: &FontData) -> Vec<OcredChar> {
+    let letter_height = get_line_y_threshold(&line);
+    let max_gap = get_space_threshold(font_data) as i64;
+    let mut ocred_chars: Vec<OcredChar> = Vec::new();
+    let mut last_letter: Option<RelMatrix> = None;
+    for rel_bitmap in line {
+        let char_matches = match_letter_to_font(&rel_bitmap.bitmap, font_data);
+        if let (Some(prev_letter), Some(prev_char)) = (&last_letter, ocred_chars.last()) {
+            if are_strokes_side_by_side(prev_letter, &rel_bitmap, letter_height, max_gap) {
+                let merged = merge_char_parts(prev_letter, &rel_bitmap);
+                let merged_matches = match_letter_to_font(&merged.bitmap, font_data);
+                let worse_score = min(prev_char.char_matches[0].match_score, char_matches[0].match_score);
+                if merged_matches[0].match_score >= worse_score {
+                    ocred_chars.pop();
+                    ocred_chars.push(OcredChar { bounds: merged.bounds, char_matches: merged_matches });
+                    last_letter = None;
+                    continue;
+                }
+            }
+        }
         let ocred_char: OcredChar = OcredChar {
             bounds: rel_bitmap.bounds,
             char_matches,
         };
         ocred_chars.push(ocred_char);
+        last_letter = Some(rel_bitmap);
+    }
+    return ocred_chars;
+}
+
+/// segment the letters, then compare resulting bitmap to every character in the font
+fn recognize_letter_lines(process: &mut OcrProcess, font_data: &FontData) -> Vec<Vec<OcredChar>> {
+    return segment_letters(process)
+        .into_iter()
+        .map(|line| recognize_line(line, font_data))
+        .collect();
+}
+
+/// cut the lines into full-width cells and compare each to the chars of the font,
+/// ruby cells are scaled up to the font size first
+fn recognize_cells(process: &mut OcrProcess, font_data: &FontData, ignore_ruby: bool) -> Vec<Vec<OcredChar>> {
+    let mut lines = Vec::new();
+    for line in segment_cell_lines(find_pieces(process), font_data, ignore_ruby) {
+        let mut ocred_chars = Vec::new();
+        for cell in line.cells {
+            let char_matches = if line.is_ruby {
+                match_letter_to_font(&resize_bitmap(&cell.bitmap, 1.0 / line.size_ratio), font_data)
+            } else {
+                match_letter_to_font(&cell.bitmap, font_data)
+            };
+            ocred_chars.push(OcredChar { bounds: cell.bounds, char_matches });
+        }
+        lines.push(ocred_chars);
     }
+    return lines;
+}
+
+/// recognized chars of each line, top to bottom
+fn recognize_lines(process: &mut OcrProcess, font_data: &FontData) -> Vec<Vec<OcredChar>> {
+    return match font_data.segmentation {
+        Segmentation::Letters => recognize_letter_lines(process, font_data),
+        Segmentation::FullWidthCells { ignore_ruby } => recognize_cells(process, font_data, ignore_ruby),
+    };
+}
+
+/// gap between letters that makes a space, Japanese has no spaces and
+/// the gaps after punctuation in its cells are wide anyway
+fn get_space_threshold(font_data: &FontData) -> i32 {
+    return match font_data.segmentation {
+        Segmentation::Letters => (SPACE_X_THRESHOLD * font_data.scale / DEFAULT_SCALE).round() as i32,
+        Segmentation::FullWidthCells { .. } => i32::MAX,
+    };
+}
 
-    let lines = group_chars_by_line(ocred_chars);
+fn lines_to_text(lines: Vec<Vec<OcredChar>>, space_threshold: i32) -> Vec<String> {
+    let mut text_lines = Vec::new();
     for line in lines {
+        if line.is_empty() {
+            continue;
+        }
+        let mut text = String::new();
         let mut end_x = line[0].bounds.end.x;
         for ocred_char in line {
-            if ocred_char.bounds.start.x as i32 - end_x as i32 > 5 {
-                print!(" ");
+            let is_space = (ocred_char.bounds.start.x - end_x) as i32 > space_threshold;
+            if is_space {
+                text.push(' ');
             }
             end_x = ocred_char.bounds.end.x;
             if ocred_char.char_matches[0].match_score > 8000000 {
-                print!("{}", ocred_char.char_matches[0].char);
+                text.push_str(&ocred_char.char_matches[0].char);
             } else {
-                print!("_")
+                text.push('_');
             }
         }
-        println!();
+        text_lines.push(text);
+    }
+    return text_lines;
+}
+
+/// recognize hardsub text lines in a frame that is already in memory
+pub fn ocr_frame(ocr_frame: &SubsOcrFrame, font_data: &FontData, region: &FrameRegion) -> Vec<String> {
+    let mut process = OcrProcess::init(ocr_frame, region);
+    let lines = recognize_lines(&mut process, font_data);
+    return lines_to_text(lines, get_space_threshold(font_data));
+}
+
+/// OCR a frame previously dumped by detect_still_frames() and print the
+/// text, the white-only debug image is saved next to the frame files
+pub fn ocr_out_from_image(
+    frames_dir: &str,
+    frame_name: &str,
+    font: &mut FontSelection,
+    region: &FrameRegion,
+) -> Result<Vec<String>> {
+    let ocr_frame = SubsOcrFrame::load(frames_dir, frame_name)?;
+    let font_data = font.get_font(&ocr_frame, region);
+    let mut process = OcrProcess::init(&ocr_frame, region);
+
+    let ocred_lines = recognize_lines(&mut process, font_data);
+
+    let debug_name = format!("{}_white_only", frame_name);
+    process.save_file(frames_dir, &debug_name)
+        .map_err(Error::io(format!("Failed to save {}/{}.ppm", frames_dir, debug_name)))?;
+
+    let lines = lines_to_text(ocred_lines, get_space_threshold(font_data));
+    for line in &lines {
+        println!("{}", line);
     }
+    return Ok(lines);
 }
@@ -0,0 +1,165 @@
+//! frames piped to stdin, like from `ffmpeg -f rawvideo -pix_fmt rgb24 -` or from a capture
+//! tool, either as bare RGB24 bitmaps of a known size or as concatenated P6 PPM images
+
+use std::io::{self, BufRead, BufReader, Read};
+use crate::error::{Error, Result};
+use crate::frame_time::{FrameTime, TimeRange};
+use crate::ppm_format;
+use crate::rgb_frame::RgbFrame;
+
+/// input path that means stdin
+pub const STDIN_INPUT: &str = "-";
+
+const DEFAULT_PIPE_FRAME_RATE: f64 = 25.0;
+
+#[derive(Debug, Copy, Clone)]
+pub enum PipeFormat {
+    /// each frame is width * height * 3 bytes, nothing in between
+    Raw { width: usize, height: usize },
+    /// each frame is a whole P6 PPM image with the header
+    Ppm,
+}
+
+/// nothing in the stream tells the time, so it is calculated from the frame rate
+#[derive(Debug, Copy, Clone)]
+pub struct PipeInput {
+    pub format: PipeFormat,
+    pub frame_rate: f64,
+}
+
+impl Default for PipeInput {
+    fn default() -> PipeInput {
+        return PipeInput { format: PipeFormat::Ppm, frame_rate: DEFAULT_PIPE_FRAME_RATE };
+    }
+}
+
+pub fn is_pipe_input(input: &str) -> bool {
+    return input == STDIN_INPUT;
+}
+
+/// fills the buffer completely, returns false if stream ended right before the
+/// first byte, a stream that ends in the middle of the buffer is an error
+fn read_whole(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream ended in the middle of a frame")),
+            Ok(read) => filled += read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {},
+            Err(error) => return Err(error),
+        }
+    }
+    return Ok(true);
+}
+
+fn read_next_bitmap(reader: &mut impl BufRead, format: &PipeFormat) -> io::Result<Option<(usize, usize, Vec<u8>)>> {
+    return match format {
+        PipeFormat::Raw { width, height } => {
+            let mut bitmap = vec![0; width * height * 3];
+            Ok(if read_whole(reader, &mut bitmap)? { Some((*width, *height, bitmap)) } else { None })
+        },
+        PipeFormat::Ppm => match ppm_format::read_header(reader)? {
+            Some((width, height)) => {
+                let mut bitmap = vec![0; width * height * 3];
+                if !read_whole(reader, &mut bitmap)? {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream ended after a PPM header"));
+                }
+                Ok(Some((width, height, bitmap)))
+            },
+            None => Ok(None),
+        },
+    };
+}
+
+/// frames from the reader till it ends, returns the moment right after the last frame,
+/// a frame cut off by the end of the stream is dropped, on_frame returns false to stop
+pub fn read_pipe_frames(
+    reader: impl Read,
+    pipe: &PipeInput,
+    range: &TimeRange,
+    mut on_frame: impl FnMut(RgbFrame) -> bool,
+) -> Result<FrameTime> {
+    let mut reader = BufReader::new(reader);
+    let time_at = |frame_index: usize| FrameTime {
+        frame_index,
+        pts: None,
+        millis: (frame_index as f64 * 1000.0 / pipe.frame_rate).round() as u64,
+    };
+    let mut frame_index = 0;
+    loop {
+        let time = time_at(frame_index);
+        if range.is_after_end(&time) {
+            break;
+        }
+        let (width, height, bitmap) = match read_next_bitmap(&mut reader, &pipe.format) {
+            Ok(Some(frame)) => frame,
+            Ok(None) => break,
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => {
+                eprintln!("Dropping frame {}: {}", frame_index, error);
+                break;
+            },
+            Err(error) => return Err(Error::io(format!("Failed to read frame {} from the pipe", frame_index))(error)),
+        };
+        frame_index += 1;
+        if !range.is_before_start(&time) && !on_frame(RgbFrame::new(time, width, height, bitmap)) {
+            break;
+        }
+    }
+    return Ok(time_at(frame_index));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(bytes: &[u8], format: PipeFormat) -> Result<(Vec<RgbFrame>, FrameTime)> {
+        let pipe = PipeInput { format, ..PipeInput::default() };
+        let mut frames = Vec::new();
+        let end = read_pipe_frames(bytes, &pipe, &TimeRange::default(), |frame| {
+            frames.push(frame);
+            true
+        })?;
+        return Ok((frames, end));
+    }
+
+    #[test]
+    fn splits_ppm_stream_by_headers() {
+        let mut bytes = b"P6\n2 1\n255\n".to_vec();
+        bytes.extend_from_slice(&[1, 2, 3, 4, 5, 6]);
+        bytes.extend_from_slice(b"P6 # written by a capture tool\n1 1 255\n");
+        bytes.extend_from_slice(&[7, 8, 9]);
+        let (frames, end) = read_all(&bytes, PipeFormat::Ppm).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!((frames[0].width, frames[0].height), (2, 1));
+        assert_eq!(frames[0].bitmap, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(frames[1].bitmap, vec![7, 8, 9]);
+        assert_eq!(frames[1].time.millis, 40);
+        assert_eq!(end.frame_index, 2);
+    }
+
+    #[test]
+    fn drops_raw_frame_cut_off_by_the_end() {
+        let (frames, end) = read_all(&[0; 15], PipeFormat::Raw { width: 2, height: 1 }).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(end.frame_index, 2);
+        assert_eq!(end.millis, 80);
+    }
+
+    #[test]
+    fn rejects_other_images_than_p6() {
+        assert!(read_all(b"P3\n1 1\n255\n0 0 0\n", PipeFormat::Ppm).is_err());
+        assert!(read_all(b"P6\n1 1\n65535\n", PipeFormat::Ppm).is_err());
+    }
+
+    #[test]
+    fn stops_when_asked() {
+        let pipe = PipeInput { format: PipeFormat::Raw { width: 1, height: 1 }, ..PipeInput::default() };
+        let mut frame_count = 0;
+        read_pipe_frames(&[0u8; 30][..], &pipe, &TimeRange::default(), |_| {
+            frame_count += 1;
+            frame_count < 3
+        }).unwrap();
+        assert_eq!(frame_count, 3);
+    }
+}
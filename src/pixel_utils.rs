@@ -1,4 +1,6 @@
 
+use serde::{Deserialize, Serialize};
+
 /**
  * @see https://stackoverflow.com/a/9493060/2750743
  *
@@ -88,7 +90,7 @@ impl Color {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Point {
     pub x: i64,
     pub y: i64,
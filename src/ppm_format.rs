@@ -5,19 +5,22 @@
 //!
 //! this module provides functions that read/save the file binary representation
 
+use crate::error::{Error, Result};
 use crate::pixel_utils::{Color, Point};
+use std::borrow::Cow;
 
-pub struct PpmData {
+/// bytes of a decoded file are owned, a wrapped bitmap may be borrowed
+pub struct PpmData<'a> {
     pub version: String,
     pub width: usize,
     pub height: usize,
     pub color_depth: usize,
     /** you don't need this, it's stored just to keep `bitmap` slice alive */
     bitmap_start: usize,
-    raw_bytes: Vec<u8>,
+    raw_bytes: Cow<'a, [u8]>,
 }
 
-impl PpmData {
+impl PpmData<'_> {
     pub fn get_bitmap(&self) -> &[u8] {
         return &self.raw_bytes[self.bitmap_start ..];
     }
@@ -37,11 +40,27 @@ impl PpmData {
     }
 }
 
+/// wraps an already decoded RGB24 bitmap, like the one we get from ffmpeg, so
+/// that it could be passed to the OCR without dumping it to a file first
+pub fn from_bitmap<'a>(width: usize, height: usize, bitmap: impl Into<Cow<'a, [u8]>>) -> PpmData<'a> {
+    return PpmData {
+        version: String::from("P6"),
+        width,
+        height,
+        color_depth: 255,
+        bitmap_start: 0,
+        raw_bytes: bitmap.into(),
+    };
+}
+
 pub fn make_header(width: usize, height: usize) -> String {
     return format!("P6\n{} {}\n255\n", width, height);
 }
 
-pub fn decode(raw_bytes: Vec<u8>) -> PpmData {
+pub fn decode(raw_bytes: Vec<u8>) -> Result<PpmData<'static>> {
+    let invalid = |message: String| Error::Image(format!("Invalid PPM file: {}", message));
+    let parse_number = |word: &str| word.trim().parse::<usize>()
+        .map_err(|_| invalid(format!("expected a number in the header, got {:?}", word)));
     let mut version = String::from("");
     let mut width = 0;
     let mut height = 0;
@@ -54,21 +73,65 @@ pub fn decode(raw_bytes: Vec<u8>) -> PpmData {
             if line_breaks_found == 1 {
                 version = header_line_buffer;
             } else if line_breaks_found == 2 {
-                let mut split = header_line_buffer
-                    .split(" ")
-                    .map(|part| part.parse::<usize>().unwrap());
-                width = split.next().unwrap();
-                height = split.next().unwrap();
+                let mut split = header_line_buffer.split(" ");
+                width = parse_number(split.next().unwrap_or(""))?;
+                height = parse_number(split.next().unwrap_or(""))?;
             } else if line_breaks_found == 3 {
-                let color_depth = header_line_buffer.parse::<usize>().unwrap();
+                let color_depth = parse_number(&header_line_buffer)?;
                 let bitmap_start = i + 1;
-                let bitmap = &raw_bytes[i + 1 ..];
-                return PpmData { version, width, height, color_depth, bitmap_start, raw_bytes };
+                return Ok(PpmData { version, width, height, color_depth, bitmap_start, raw_bytes: Cow::Owned(raw_bytes) });
             }
             header_line_buffer = String::from("");
         } else {
             header_line_buffer.push(raw_bytes[i] as char);
         }
     }
-    panic!("Ebanij vrot, missing \\n in ppm file");
+    return Err(invalid("missing \\n in the header".to_string()));
+}
+
+/// next whitespace separated word of the header, comments starting with '#' are skipped,
+/// the single whitespace byte after the word is consumed as well, None if stream ended
+fn read_header_word(reader: &mut impl std::io::BufRead) -> std::io::Result<Option<String>> {
+    let mut word = String::new();
+    let mut is_comment = false;
+    let mut byte = [0; 1];
+    loop {
+        if reader.read(&mut byte)? == 0 {
+            return Ok(if word.is_empty() { None } else { Some(word) });
+        }
+        let c = byte[0] as char;
+        if is_comment {
+            is_comment = c != '\n';
+        } else if c == '#' && word.is_empty() {
+            is_comment = true;
+        } else if c.is_ascii_whitespace() {
+            if !word.is_empty() {
+                return Ok(Some(word));
+            }
+        } else {
+            word.push(c);
+        }
+    }
+}
+
+/// reads the header of the next image in a stream of concatenated P6 images, like
+/// `ffmpeg -f image2pipe -vcodec ppm` writes, returns None if stream ended before it
+pub fn read_header(reader: &mut impl std::io::BufRead) -> std::io::Result<Option<(usize, usize)>> {
+    let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+    let version = match read_header_word(reader)? {
+        Some(version) => version,
+        None => return Ok(None),
+    };
+    if version != "P6" {
+        return Err(invalid(format!("expected P6 PPM image, got {}", version)));
+    }
+    let mut numbers = [0; 3];
+    for number in numbers.iter_mut() {
+        let word = read_header_word(reader)?.ok_or_else(|| invalid("stream ended in PPM header".to_string()))?;
+        *number = word.parse().map_err(|_| invalid(format!("expected a number in PPM header, got {}", word)))?;
+    }
+    if numbers[2] != 255 {
+        return Err(invalid(format!("only color depth 255 is supported, got {}", numbers[2])));
+    }
+    return Ok(Some((numbers[0], numbers[1])));
 }
@@ -3,8 +3,9 @@
 
 use crate::pixel_utils::Point;
 use std::cmp::{min, max};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Bounds {
     pub start: Point,
     pub end: Point,
@@ -20,6 +21,17 @@ impl Bounds {
     }
 }
 
+/// grows the box, if any, so that the point would be inside of it
+pub fn extend_bounds(bounds: Option<Bounds>, point: Point) -> Bounds {
+    return match bounds {
+        None => Bounds { start: point, end: point },
+        Some(Bounds { start, end }) => Bounds {
+            start: Point { x: min(start.x, point.x), y: min(start.y, point.y) },
+            end: Point { x: max(end.x, point.x), y: max(end.y, point.y) },
+        },
+    };
+}
+
 pub struct PixelCoverage {
     pub x: u32,
     pub y: u32,
@@ -50,6 +62,7 @@ pub fn get_bounds(letter_pixels: &Vec<PixelCoverage>) -> Bounds {
     };
 }
 
+#[derive(Clone)]
 pub struct RelMatrix {
     pub bounds: Bounds,
     pub bitmap: Vec<Vec<f32>>,
@@ -70,3 +83,71 @@ pub fn make_rel_bitmap(letter_pixels: Vec<PixelCoverage>) -> RelMatrix {
         bitmap: rel_bitmap,
     };
 }
+
+/// bitmap scaled by the factor with bilinear interpolation, like small ruby chars scaled up to the font size
+pub fn resize_bitmap(bitmap: &[Vec<f32>], factor: f32) -> Vec<Vec<f32>> {
+    let src_width = bitmap.len();
+    let src_height = bitmap[0].len();
+    let width = max(1, (src_width as f32 * factor).round() as usize);
+    let height = max(1, (src_height as f32 * factor).round() as usize);
+    let get_source = |position: usize, src_size: usize| {
+        let src = ((position as f32 + 0.5) / factor - 0.5).max(0.0).min((src_size - 1) as f32);
+        let start = src.floor() as usize;
+        return (start, min(start + 1, src_size - 1), src - start as f32);
+    };
+    let mut resized = vec![vec![0.0; height]; width];
+    for x in 0..width {
+        let (x0, x1, fx) = get_source(x, src_width);
+        for y in 0..height {
+            let (y0, y1, fy) = get_source(y, src_height);
+            let top = bitmap[x0][y0] * (1.0 - fx) + bitmap[x1][y0] * fx;
+            let bottom = bitmap[x0][y1] * (1.0 - fx) + bitmap[x1][y1] * fx;
+            resized[x][y] = top * (1.0 - fy) + bottom * fy;
+        }
+    }
+    return resized;
+}
+
+/// cells per side of the grid of a Signature
+const SIGNATURE_SIDE: usize = 6;
+
+/// how much a twice as wide or as tall shape adds to the distance, in average cell coverages
+const SIGNATURE_ASPECT_WEIGHT: f32 = 0.5;
+
+/// rough shape of a bitmap: coverage averaged in a grid stretched over it and its proportions,
+/// cheap to compare, so that big charsets are narrowed down before the pixel by pixel comparison
+#[derive(Debug, Clone)]
+pub struct Signature {
+    grid: [f32; SIGNATURE_SIDE * SIGNATURE_SIDE],
+    /// ln of width / height
+    aspect: f32,
+}
+
+impl Signature {
+    pub fn new(bitmap: &[Vec<f32>]) -> Signature {
+        let width = bitmap.len();
+        let height = bitmap[0].len();
+        let mut sums = [0.0; SIGNATURE_SIDE * SIGNATURE_SIDE];
+        let mut counts = [0usize; SIGNATURE_SIDE * SIGNATURE_SIDE];
+        for (x, column) in bitmap.iter().enumerate() {
+            for (y, coverage) in column.iter().enumerate() {
+                let cell = (y * SIGNATURE_SIDE / height) * SIGNATURE_SIDE + x * SIGNATURE_SIDE / width;
+                sums[cell] += coverage;
+                counts[cell] += 1;
+            }
+        }
+        let mut grid = [0.0; SIGNATURE_SIDE * SIGNATURE_SIDE];
+        for cell in 0..grid.len() {
+            // cells get no pixels when the bitmap is narrower than the grid
+            grid[cell] = if counts[cell] > 0 { sums[cell] / counts[cell] as f32 } else { 0.0 };
+        }
+        return Signature { grid, aspect: (width as f32 / height as f32).ln() };
+    }
+
+    /// the lower the more alike
+    pub fn get_distance(&self, other: &Signature) -> f32 {
+        let grid_distance: f32 = self.grid.iter().zip(other.grid.iter()).map(|(a, b)| (a - b).abs()).sum();
+        let aspect_distance = (self.aspect - other.aspect).abs() / 2f32.ln();
+        return grid_distance / self.grid.len() as f32 + aspect_distance * SIGNATURE_ASPECT_WEIGHT;
+    }
+}
@@ -0,0 +1,77 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::util::frame::video::Video;
+use std::sync::OnceLock;
+use crate::frame_time::FrameTime;
+use crate::frame_blocks::get_block_checksums;
+
+/// clockwise rotation the picture has to be displayed with
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Rotation {
+    None,
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+/// decoded frame as tightly packed R,G,B bytes, row by row, unlike ffmpeg frame it
+/// owns its bytes, so it can be shared between the decoding and the analysis threads
+pub struct RgbFrame {
+    pub time: FrameTime,
+    pub width: usize,
+    pub height: usize,
+    pub bitmap: Vec<u8>,
+    /// computed by whichever thread compares this frame first, see frame_blocks
+    block_checksums: OnceLock<Vec<u64>>,
+}
+
+impl RgbFrame {
+    pub fn new(time: FrameTime, width: usize, height: usize, bitmap: Vec<u8>) -> RgbFrame {
+        return RgbFrame { time, width, height, bitmap, block_checksums: OnceLock::new() };
+    }
+
+    /// ffmpeg pads each row of the bitmap to the line size, the padding is dropped here
+    pub fn from_video(time: FrameTime, frame: &Video) -> RgbFrame {
+        let width = frame.width() as usize;
+        let height = frame.height() as usize;
+        let stride = frame.stride(0);
+        let data = frame.data(0);
+        let mut bitmap = Vec::with_capacity(width * height * 3);
+        for y in 0..height {
+            bitmap.extend_from_slice(&data[y * stride..y * stride + width * 3]);
+        }
+        return RgbFrame::new(time, width, height, bitmap);
+    }
+
+    pub fn is_same_size(&self, other: &RgbFrame) -> bool {
+        return self.width == other.width && self.height == other.height;
+    }
+
+    pub fn rotate(self, rotation: Rotation) -> RgbFrame {
+        if rotation == Rotation::None {
+            return self;
+        }
+        let (width, height) = match rotation {
+            Rotation::Clockwise90 | Rotation::Clockwise270 => (self.height, self.width),
+            _ => (self.width, self.height),
+        };
+        let mut bitmap = vec![0; self.bitmap.len()];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (new_x, new_y) = match rotation {
+                    Rotation::Clockwise90 => (self.height - 1 - y, x),
+                    Rotation::Clockwise180 => (self.width - 1 - x, self.height - 1 - y),
+                    _ => (y, self.width - 1 - x),
+                };
+                let old_index = (y * self.width + x) * 3;
+                let new_index = (new_y * width + new_x) * 3;
+                bitmap[new_index..new_index + 3].copy_from_slice(&self.bitmap[old_index..old_index + 3]);
+            }
+        }
+        return RgbFrame::new(self.time, width, height, bitmap);
+    }
+
+    pub fn get_block_checksums(&self) -> &[u64] {
+        return self.block_checksums.get_or_init(|| get_block_checksums(&self.bitmap, self.width, self.height));
+    }
+}
@@ -0,0 +1,298 @@
+//! detects scene cuts and fades from/to black by the whole frame brightness histogram, not
+//! limited to the region, only ignored parts are left out, so that background changes are
+//! reported as such, and text that arrived together with the new background is looked for
+//! once the scene is stable, instead of being lost in a huge change
+
+use crate::frame_time::FrameTime;
+use crate::pixel_utils::{Color, Point};
+use crate::rel_matrix::{extend_bounds, Bounds};
+use serde::Serialize;
+
+const HISTOGRAM_BINS: usize = 64;
+
+/// portion of pixels that moved to a different brightness bin for the change to be a cut,
+/// text appearing on a panel moves just a couple of percent
+const SCENE_CUT_HISTOGRAM_DELTA: f64 = 0.4;
+
+/// mean brightness in range [0..255] has to change at least that much every frame of a fade
+const FADE_MIN_STEP: f64 = 0.75;
+
+/// shorter brightness ramps are just something moving on the screen
+const FADE_MIN_FRAMES: usize = 4;
+
+/// mean brightness has to change at least that much during the whole fade
+const FADE_MIN_TOTAL: f64 = 24.0;
+
+/// the scene is considered stable after that many frames without considerable changes
+const SCENE_STABLE_FRAMES: usize = 3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SceneChangeKind {
+    Cut,
+    FadeIn,
+    FadeOut,
+}
+
+#[derive(Debug, Copy, Clone, Serialize)]
+pub struct SceneChange {
+    pub kind: SceneChangeKind,
+    /// for a cut, both start and end are the first frame of the new scene
+    pub start: FrameTime,
+    pub end: FrameTime,
+}
+
+/// brightness distribution of one frame, it does not depend on other frames, so it is
+/// computed on the analysis worker threads
+#[derive(Clone)]
+pub struct LumaStats {
+    histogram: [u32; HISTOGRAM_BINS],
+    pixels: u32,
+    mean: f64,
+}
+
+fn get_luma(rgb: &[u8]) -> u32 {
+    return (rgb[0] as u32 * 299 + rgb[1] as u32 * 587 + rgb[2] as u32 * 114) / 1000;
+}
+
+pub fn get_luma_stats(bitmap: &[u8], pixel_mask: &[bool]) -> LumaStats {
+    let mut histogram = [0; HISTOGRAM_BINS];
+    let mut pixels = 0;
+    let mut luma_sum: u64 = 0;
+    for (rgb, is_considered) in bitmap.chunks(3).zip(pixel_mask) {
+        if *is_considered {
+            let luma = get_luma(rgb);
+            histogram[luma as usize * HISTOGRAM_BINS / 256] += 1;
+            luma_sum += luma as u64;
+            pixels += 1;
+        }
+    }
+    let mean = if pixels > 0 { luma_sum as f64 / pixels as f64 } else { 0.0 };
+    return LumaStats { histogram, pixels, mean };
+}
+
+/// portion of pixels that would have to change their brightness bin
+fn get_histogram_delta(a: &LumaStats, b: &LumaStats) -> f64 {
+    if a.pixels == 0 || a.pixels != b.pixels {
+        return 0.0;
+    }
+    let moved: u32 = a.histogram.iter()
+        .zip(b.histogram.iter())
+        .map(|(a_count, b_count)| if a_count > b_count { a_count - b_count } else { b_count - a_count })
+        .sum();
+    return moved as f64 / 2.0 / a.pixels as f64;
+}
+
+struct BrightnessRamp {
+    start: FrameTime,
+    end: FrameTime,
+    start_mean: f64,
+    is_rising: bool,
+    frames: usize,
+}
+
+pub struct SceneDetector {
+    last_stats: Option<LumaStats>,
+    ramp: Option<BrightnessRamp>,
+    /// number of stable frames since the last scene change, if it is not stable yet
+    unstable_frames_ago: Option<usize>,
+}
+
+impl SceneDetector {
+    pub fn new() -> SceneDetector {
+        return SceneDetector { last_stats: None, ramp: None, unstable_frames_ago: None };
+    }
+
+    /// brightness ramp is reported as a fade when it stops
+    fn follow_ramp(&mut self, time: FrameTime, last_mean: f64, mean: f64) -> Option<SceneChange> {
+        let step = mean - last_mean;
+        let is_ramp_step = step.abs() >= FADE_MIN_STEP;
+        if let Some(ramp) = &mut self.ramp {
+            if is_ramp_step && ramp.is_rising == (step > 0.0) {
+                ramp.end = time;
+                ramp.frames += 1;
+                return None;
+            }
+        }
+        let finished = self.ramp.take();
+        if is_ramp_step {
+            self.ramp = Some(BrightnessRamp {
+                start: time,
+                end: time,
+                start_mean: last_mean,
+                is_rising: step > 0.0,
+                frames: 1,
+            });
+        }
+        return match finished {
+            Some(ramp) if ramp.frames >= FADE_MIN_FRAMES
+                && (last_mean - ramp.start_mean).abs() >= FADE_MIN_TOTAL => Some(SceneChange {
+                kind: if ramp.is_rising { SceneChangeKind::FadeIn } else { SceneChangeKind::FadeOut },
+                start: ramp.start,
+                end: ramp.end,
+            }),
+            _ => None,
+        };
+    }
+
+    /// feed stats of each next frame, see get_luma_stats, none if the frame did not change
+    pub fn feed(&mut self, time: FrameTime, stats: Option<LumaStats>) -> Option<SceneChange> {
+        let stats = match (stats, &self.last_stats) {
+            (Some(stats), _) => stats,
+            (None, Some(last_stats)) => last_stats.clone(),
+            (None, None) => return None,
+        };
+        let scene_change = match &self.last_stats {
+            Some(last_stats) => {
+                let last_mean = last_stats.mean;
+                if get_histogram_delta(last_stats, &stats) > SCENE_CUT_HISTOGRAM_DELTA {
+                    self.ramp = None;
+                    Some(SceneChange { kind: SceneChangeKind::Cut, start: time, end: time })
+                } else {
+                    self.follow_ramp(time, last_mean, stats.mean)
+                }
+            },
+            None => None,
+        };
+        self.last_stats = Some(stats);
+        // a ramp alone is not a change yet, text appearing moves the mean brightness a bit too
+        if scene_change.is_some() {
+            self.unstable_frames_ago = Some(0);
+        }
+        return scene_change;
+    }
+
+    /// returns true once, on the frame when the scene got stable after a cut or a fade
+    pub fn is_settled_after_change(&mut self, is_frame_stable: bool) -> bool {
+        if let Some(stable_frames) = self.unstable_frames_ago {
+            if !is_frame_stable || self.ramp.is_some() {
+                self.unstable_frames_ago = Some(0);
+            } else if stable_frames + 1 >= SCENE_STABLE_FRAMES {
+                self.unstable_frames_ago = None;
+                return true;
+            } else {
+                self.unstable_frames_ago = Some(stable_frames + 1);
+            }
+        }
+        return false;
+    }
+}
+
+/// points that look like hardsub letters in a frame by themselves, without a previous frame
+/// to compare to: nearly white points that have a black outline point close by
+pub struct SubtitlePoints {
+    pub count: usize,
+    pub bounds: Option<Bounds>,
+    /// same format as text_only_frame of a text change, the points and black everywhere else
+    pub text_only_bitmap: Vec<u8>,
+}
+
+/// how far from a letter point its outline may be
+const OUTLINE_DISTANCE: usize = 2;
+
+pub fn find_subtitle_points(bitmap: &[u8], width: usize, height: usize, pixel_mask: &[bool]) -> SubtitlePoints {
+    let get_color = |x: usize, y: usize| {
+        let byte_index = (y * width + x) * 3;
+        Color { r: bitmap[byte_index], g: bitmap[byte_index + 1], b: bitmap[byte_index + 2] }
+    };
+    let mut text_only_bitmap = vec![0; bitmap.len()];
+    let mut count = 0;
+    let mut bounds: Option<Bounds> = None;
+    for y in OUTLINE_DISTANCE..height.saturating_sub(OUTLINE_DISTANCE) {
+        for x in OUTLINE_DISTANCE..width.saturating_sub(OUTLINE_DISTANCE) {
+            if !pixel_mask[y * width + x] || !get_color(x, y).is_nearly_white() {
+                continue;
+            }
+            let outline_options = [
+                (x - OUTLINE_DISTANCE, y),
+                (x + OUTLINE_DISTANCE, y),
+                (x, y - OUTLINE_DISTANCE),
+                (x, y + OUTLINE_DISTANCE),
+            ];
+            let has_outline = outline_options
+                .iter()
+                .any(|(nx, ny)| get_color(*nx, *ny).is_closely_black());
+            if has_outline {
+                let byte_index = (y * width + x) * 3;
+                text_only_bitmap[byte_index..byte_index + 3].copy_from_slice(&bitmap[byte_index..byte_index + 3]);
+                count += 1;
+                bounds = Some(extend_bounds(bounds, Point { x: x as i64, y: y as i64 }));
+            }
+        }
+    }
+    return SubtitlePoints { count, bounds, text_only_bitmap };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIXELS: usize = 100;
+
+    fn time(frame_index: usize) -> FrameTime {
+        return FrameTime { frame_index, pts: None, millis: frame_index as u64 * 40 };
+    }
+
+    /// the first bright_pixels are white, the rest has the given brightness
+    fn stats(brightness: u8, bright_pixels: usize) -> LumaStats {
+        let mut bitmap = vec![brightness; PIXELS * 3];
+        bitmap[..bright_pixels * 3].iter_mut().for_each(|byte| *byte = 255);
+        return get_luma_stats(&bitmap, &[true; PIXELS]);
+    }
+
+    #[test]
+    fn masked_pixels_are_not_counted() {
+        let mut mask = [true; PIXELS];
+        mask[..50].iter_mut().for_each(|is_considered| *is_considered = false);
+        let stats = get_luma_stats(&[200; PIXELS * 3], &mask);
+        assert_eq!(stats.pixels, 50);
+        assert_eq!(stats.mean, 200.0);
+    }
+
+    #[test]
+    fn new_background_is_a_cut() {
+        let mut detector = SceneDetector::new();
+        assert!(detector.feed(time(0), Some(stats(30, 0))).is_none());
+        let change = detector.feed(time(1), Some(stats(180, 0))).unwrap();
+        assert_eq!(change.kind, SceneChangeKind::Cut);
+        assert_eq!(change.start.frame_index, 1);
+    }
+
+    #[test]
+    fn small_text_is_not_a_cut() {
+        let mut detector = SceneDetector::new();
+        assert!(detector.feed(time(0), Some(stats(100, 0))).is_none());
+        assert!(detector.feed(time(1), Some(stats(100, 5))).is_none());
+        assert!(detector.feed(time(2), None).is_none());
+    }
+
+    #[test]
+    fn slow_brightness_ramp_is_a_fade_out() {
+        let mut detector = SceneDetector::new();
+        let mut changes = Vec::new();
+        // a gradient darkening by one step per frame, so that few pixels change the bin at once
+        for frame_index in 0..40 {
+            let offset = 100 - frame_index.clamp(2, 32) as u8;
+            let bitmap: Vec<u8> = (0..PIXELS as u8).flat_map(|pixel| vec![offset + pixel; 3]).collect();
+            let stats = get_luma_stats(&bitmap, &[true; PIXELS]);
+            changes.extend(detector.feed(time(frame_index), Some(stats)));
+        }
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, SceneChangeKind::FadeOut);
+        assert_eq!(changes[0].start.frame_index, 3);
+        assert_eq!(changes[0].end.frame_index, 32);
+    }
+
+    #[test]
+    fn scene_settles_after_stable_frames() {
+        let mut detector = SceneDetector::new();
+        detector.feed(time(0), Some(stats(30, 0)));
+        detector.feed(time(1), Some(stats(180, 0)));
+        assert!(!detector.is_settled_after_change(true));
+        assert!(!detector.is_settled_after_change(false));
+        assert!(!detector.is_settled_after_change(true));
+        assert!(!detector.is_settled_after_change(true));
+        assert!(detector.is_settled_after_change(true));
+        assert!(!detector.is_settled_after_change(true));
+    }
+}
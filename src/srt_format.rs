@@ -0,0 +1,63 @@
+//! SubRip is the most basic subtitles format: numbered blocks of a time
+//! range line followed by text lines, blocks are separated by an empty line
+//! see https://en.wikipedia.org/wiki/SubRip
+//!
+//! this module provides functions that make the file text representation
+
+pub struct SrtCue {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub lines: Vec<String>,
+}
+
+/// formats milliseconds as 01:02:03,456
+pub fn format_timestamp(ms: u64) -> String {
+    let hours = ms / 3_600_000;
+    let minutes = ms / 60_000 % 60;
+    let seconds = ms / 1000 % 60;
+    let millis = ms % 1000;
+    return format!("{:02}:{:02}:{:02},{:03}", hours, minutes, seconds, millis);
+}
+
+pub fn encode(cues: &[SrtCue]) -> String {
+    let mut result = String::new();
+    for (i, cue) in cues.iter().enumerate() {
+        result.push_str(&format!("{}\n", i + 1));
+        result.push_str(&format!(
+            "{} --> {}\n",
+            format_timestamp(cue.start_ms),
+            format_timestamp(cue.end_ms)
+        ));
+        for line in &cue.lines {
+            result.push_str(line);
+            result.push('\n');
+        }
+        result.push('\n');
+    }
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_timestamp() {
+        assert_eq!(format_timestamp(0), "00:00:00,000");
+        assert_eq!(format_timestamp(3_723_004), "01:02:03,004");
+        assert_eq!(format_timestamp(59_999), "00:00:59,999");
+        assert_eq!(format_timestamp(100 * 3_600_000), "100:00:00,000");
+    }
+
+    #[test]
+    fn encodes_numbered_cues() {
+        let cues = vec![
+            SrtCue { start_ms: 1000, end_ms: 2500, lines: vec!["Hello".to_string(), "world".to_string()] },
+            SrtCue { start_ms: 3000, end_ms: 4000, lines: vec!["Bye".to_string()] },
+        ];
+        assert_eq!(
+            encode(&cues),
+            "1\n00:00:01,000 --> 00:00:02,500\nHello\nworld\n\n2\n00:00:03,000 --> 00:00:04,000\nBye\n\n"
+        );
+    }
+}
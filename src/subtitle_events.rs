@@ -0,0 +1,221 @@
+//! a state machine over the sequence of changed frames that turns single text change
+//! points into events: text appeared at T1, stayed stable, got cleared or replaced at T2
+
+use crate::detect_still_frames::FrameChange;
+use crate::frame_time::FrameTime;
+use crate::rel_matrix::Bounds;
+use serde::Serialize;
+
+/// if that few white points appeared compared to how many vanished, text was cleared
+/// rather than replaced, new text would have at least as many white points as old one
+const CLEARED_APPEARED_RATIO: f64 = 0.2;
+
+/// a change too small to be a text change still clears the text, if the change is inside
+/// the text box and removes at least that portion of white points the text had
+const PARTIAL_CLEAR_RATIO: f64 = 0.5;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventEnd {
+    /// text vanished, leaving an empty box or no box at all
+    Cleared,
+    /// different text appeared in place of this one
+    Replaced,
+    /// video ended while text was still displayed
+    VideoEnd,
+    /// end of the processed range was reached while text was still displayed
+    RangeEnd,
+}
+
+pub struct SubtitleEvent<T> {
+    pub start: FrameTime,
+    pub end: FrameTime,
+    pub end_reason: EventEnd,
+    /// whatever caller associated with the text, like OCR-ed lines
+    pub payload: T,
+}
+
+struct OpenEvent<T> {
+    start: FrameTime,
+    bounds: Option<Bounds>,
+    text_points: usize,
+    payload: T,
+}
+
+fn overlaps(a: &Bounds, b: &Bounds) -> bool {
+    return a.start.x <= b.end.x && b.start.x <= a.end.x
+        && a.start.y <= b.end.y && b.start.y <= a.end.y;
+}
+
+pub struct EventSegmenter<T> {
+    open: Option<OpenEvent<T>>,
+}
+
+impl<T> EventSegmenter<T> {
+    pub fn new() -> EventSegmenter<T> {
+        return EventSegmenter { open: None };
+    }
+
+    fn is_clear(&self, change: &FrameChange) -> bool {
+        let appeared = change.appeared_text_points as f64;
+        let vanished = change.vanished_text_points as f64;
+        if change.is_text_change {
+            return appeared < vanished * CLEARED_APPEARED_RATIO;
+        }
+        return match (&self.open, &change.changed_bounds) {
+            (Some(open), Some(changed_bounds)) => {
+                let is_inside = match &open.bounds {
+                    Some(text_bounds) => overlaps(text_bounds, changed_bounds),
+                    None => true,
+                };
+                is_inside
+                    && appeared < vanished * CLEARED_APPEARED_RATIO
+                    && vanished >= open.text_points as f64 * PARTIAL_CLEAR_RATIO
+            },
+            _ => false,
+        };
+    }
+
+    /// text found again after a scene change where the open text is, is the same text
+    fn is_open_over(&self, change: &FrameChange) -> bool {
+        return match (&self.open, &change.changed_bounds) {
+            (Some(open), Some(changed_bounds)) if change.is_rescan => match &open.bounds {
+                Some(text_bounds) => overlaps(text_bounds, changed_bounds),
+                None => true,
+            },
+            _ => false,
+        };
+    }
+
+    fn close(&mut self, end: FrameTime, end_reason: EventEnd) -> Option<SubtitleEvent<T>> {
+        return self.open.take().map(|open| SubtitleEvent {
+            start: open.start,
+            end,
+            end_reason,
+            payload: open.payload,
+        });
+    }
+
+    /// feed each next changed frame, returns the event this frame closed if any, make_payload
+    /// is called when text appears, returning None from it means there is no text after all
+    pub fn feed(
+        &mut self,
+        change: &FrameChange,
+        make_payload: impl FnOnce(&FrameChange) -> Option<T>,
+    ) -> Option<SubtitleEvent<T>> {
+        // with animated text, the old one is gone as soon as the new one starts typing
+        let start = change.animation_start.unwrap_or(change.time);
+        if self.is_clear(change) {
+            return self.close(start, EventEnd::Cleared);
+        }
+        if !change.is_text_change || self.is_open_over(change) {
+            return None;
+        }
+        let closed = self.close(start, EventEnd::Replaced);
+        if let Some(payload) = make_payload(change) {
+            self.open = Some(OpenEvent {
+                start,
+                bounds: change.changed_bounds,
+                text_points: change.appeared_text_points,
+                payload,
+            });
+        }
+        return closed;
+    }
+
+    /// end is the moment right after the last frame, the reason is VideoEnd or RangeEnd
+    pub fn finish(&mut self, end: FrameTime, end_reason: EventEnd) -> Option<SubtitleEvent<T>> {
+        return self.close(end, end_reason);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::change_classifier::ChangeKind;
+    use crate::pixel_utils::Point;
+
+    const TEXT_BOUNDS: Bounds = Bounds { start: Point { x: 100, y: 300 }, end: Point { x: 500, y: 350 } };
+
+    fn make_change(frame_index: usize, appeared: usize, vanished: usize, is_text_change: bool, bounds: Bounds) -> FrameChange {
+        return FrameChange {
+            time: FrameTime { frame_index, pts: None, millis: frame_index as u64 * 100 },
+            width: 640,
+            height: 360,
+            change_factor: 0.0,
+            real_points_changed: appeared + vanished,
+            kind: if is_text_change { ChangeKind::Text } else { ChangeKind::Minor },
+            is_text_change,
+            changed_bounds: Some(bounds),
+            appeared_text_points: appeared,
+            vanished_text_points: vanished,
+            animation_start: None,
+            animation_end: None,
+            scene_change: None,
+            is_rescan: false,
+            full_bitmap: Vec::new(),
+            text_only_bitmap: Vec::new(),
+        };
+    }
+
+    fn end_time(frame_index: usize) -> FrameTime {
+        return FrameTime { frame_index, pts: None, millis: frame_index as u64 * 100 };
+    }
+
+    #[test]
+    fn text_vanishing_closes_event_as_cleared() {
+        let mut segmenter = EventSegmenter::new();
+        assert!(segmenter.feed(&make_change(10, 1000, 0, true, TEXT_BOUNDS), |_| Some("first")).is_none());
+        let event = segmenter.feed(&make_change(30, 0, 1000, true, TEXT_BOUNDS), |_| Some("none")).unwrap();
+        assert_eq!(event.start.frame_index, 10);
+        assert_eq!(event.end.frame_index, 30);
+        assert_eq!(event.end_reason, EventEnd::Cleared);
+        assert_eq!(event.payload, "first");
+        assert!(segmenter.finish(end_time(50), EventEnd::VideoEnd).is_none());
+    }
+
+    #[test]
+    fn new_text_replaces_open_one() {
+        let mut segmenter = EventSegmenter::new();
+        segmenter.feed(&make_change(10, 1000, 0, true, TEXT_BOUNDS), |_| Some("first"));
+        let replaced = segmenter.feed(&make_change(20, 900, 1000, true, TEXT_BOUNDS), |_| Some("second")).unwrap();
+        assert_eq!(replaced.end.frame_index, 20);
+        assert_eq!(replaced.end_reason, EventEnd::Replaced);
+        assert_eq!(replaced.payload, "first");
+        let last = segmenter.finish(end_time(40), EventEnd::RangeEnd).unwrap();
+        assert_eq!(last.start.frame_index, 20);
+        assert_eq!(last.end_reason, EventEnd::RangeEnd);
+        assert_eq!(last.payload, "second");
+    }
+
+    #[test]
+    fn minor_change_inside_text_clears_it_only_if_it_removes_most_of_it() {
+        let mut segmenter = EventSegmenter::new();
+        segmenter.feed(&make_change(10, 1000, 0, true, TEXT_BOUNDS), |_| Some("first"));
+        assert!(segmenter.feed(&make_change(20, 0, 300, false, TEXT_BOUNDS), |_| None).is_none());
+        let elsewhere = Bounds { start: Point { x: 0, y: 0 }, end: Point { x: 50, y: 50 } };
+        assert!(segmenter.feed(&make_change(25, 0, 800, false, elsewhere), |_| None).is_none());
+        let event = segmenter.feed(&make_change(30, 0, 800, false, TEXT_BOUNDS), |_| None).unwrap();
+        assert_eq!(event.end_reason, EventEnd::Cleared);
+    }
+
+    #[test]
+    fn change_without_payload_opens_no_event() {
+        let mut segmenter: EventSegmenter<&str> = EventSegmenter::new();
+        assert!(segmenter.feed(&make_change(10, 1000, 0, true, TEXT_BOUNDS), |_| None).is_none());
+        assert!(segmenter.feed(&make_change(20, 0, 1000, true, TEXT_BOUNDS), |_| None).is_none());
+        assert!(segmenter.finish(end_time(30), EventEnd::VideoEnd).is_none());
+    }
+
+    #[test]
+    fn rescan_over_open_text_keeps_it_open() {
+        let mut segmenter = EventSegmenter::new();
+        segmenter.feed(&make_change(10, 1000, 0, true, TEXT_BOUNDS), |_| Some("first"));
+        let mut rescan = make_change(20, 1000, 0, true, TEXT_BOUNDS);
+        rescan.is_rescan = true;
+        assert!(segmenter.feed(&rescan, |_| Some("again")).is_none());
+        let event = segmenter.finish(end_time(30), EventEnd::VideoEnd).unwrap();
+        assert_eq!(event.start.frame_index, 10);
+        assert_eq!(event.payload, "first");
+    }
+}
@@ -0,0 +1,155 @@
+//! first pass over the video that counts where changed pixels of text changes land,
+//! the cells that light up in most of them are the text panel or subtitles area,
+//! so that the region of interest does not have to be set manually for each game
+
+use crate::detect_still_frames::{for_each_frame_change, DetectionParams};
+use crate::error::Result;
+use crate::frame_region::FrameRegion;
+use crate::pixel_utils::Point;
+use crate::rel_matrix::Bounds;
+use std::cmp::{max, min};
+
+/// heatmap is counted in square cells of that many pixels
+const CELL_SIZE: usize = 16;
+
+/// cell is considered touched by a text change if at least that many of its pixels changed
+const MIN_CELL_POINTS: usize = 4;
+
+/// cell is part of a text region if it was touched at least by that portion of text
+/// changes the hottest cell was touched by, text does not always span the whole panel
+const HOT_CELL_RATIO: f64 = 0.1;
+
+/// hot cells that are that many cells apart or closer are joined in one region, since
+/// there are gaps between words and lines
+const MERGE_GAP_CELLS: usize = 3;
+
+/// regions with less heat than that portion of the hottest region are noise, like
+/// a sprite that happened to change together with the text a couple of times
+const MIN_REGION_HEAT_RATIO: f64 = 0.2;
+
+pub struct TextRegionHeatmap {
+    width: usize,
+    height: usize,
+    cols: usize,
+    rows: usize,
+    /// for each cell, the number of text changes that touched it
+    heat: Vec<u32>,
+}
+
+impl TextRegionHeatmap {
+    pub fn new(width: usize, height: usize) -> TextRegionHeatmap {
+        let cols = (width + CELL_SIZE - 1) / CELL_SIZE;
+        let rows = (height + CELL_SIZE - 1) / CELL_SIZE;
+        return TextRegionHeatmap { width, height, cols, rows, heat: vec![0; cols * rows] };
+    }
+
+    pub fn add_text_change(&mut self, text_only_bitmap: &[u8]) {
+        let mut cell_points = vec![0; self.cols * self.rows];
+        for pixel_index in 0..self.width * self.height {
+            let byte_index = pixel_index * 3;
+            if text_only_bitmap[byte_index..byte_index + 3] != [0, 0, 0] {
+                let col = pixel_index % self.width / CELL_SIZE;
+                let row = pixel_index / self.width / CELL_SIZE;
+                cell_points[row * self.cols + col] += 1;
+            }
+        }
+        for (cell_index, points) in cell_points.iter().enumerate() {
+            if *points >= MIN_CELL_POINTS {
+                self.heat[cell_index] += 1;
+            }
+        }
+    }
+
+    /// flood fill of hot cells, jumping over gaps up to MERGE_GAP_CELLS
+    fn collect_cluster(&self, start: usize, is_hot: &[bool], visited: &mut [bool]) -> (Bounds, u64) {
+        let mut bounds = Bounds {
+            start: Point { x: (start % self.cols) as i64, y: (start / self.cols) as i64 },
+            end: Point { x: (start % self.cols) as i64, y: (start / self.cols) as i64 },
+        };
+        let mut total_heat = 0;
+        let mut pick_cells = vec![start];
+        visited[start] = true;
+        while let Some(cell) = pick_cells.pop() {
+            total_heat += self.heat[cell] as u64;
+            let col = cell % self.cols;
+            let row = cell / self.cols;
+            bounds.start.x = min(bounds.start.x, col as i64);
+            bounds.start.y = min(bounds.start.y, row as i64);
+            bounds.end.x = max(bounds.end.x, col as i64);
+            bounds.end.y = max(bounds.end.y, row as i64);
+            let rows_range = row.saturating_sub(MERGE_GAP_CELLS)..min(self.rows, row + MERGE_GAP_CELLS + 1);
+            for other_row in rows_range {
+                let cols_range = col.saturating_sub(MERGE_GAP_CELLS)..min(self.cols, col + MERGE_GAP_CELLS + 1);
+                for other_col in cols_range {
+                    let other = other_row * self.cols + other_col;
+                    if is_hot[other] && !visited[other] {
+                        visited[other] = true;
+                        pick_cells.push(other);
+                    }
+                }
+            }
+        }
+        return (bounds, total_heat);
+    }
+
+    /// rectangles, in pixels, where text appeared most often
+    pub fn infer_regions(&self) -> Vec<Bounds> {
+        let max_heat = self.heat.iter().cloned().max().unwrap_or(0);
+        if max_heat == 0 {
+            return Vec::new();
+        }
+        let is_hot: Vec<bool> = self.heat.iter()
+            .map(|heat| *heat > 0 && *heat as f64 >= max_heat as f64 * HOT_CELL_RATIO)
+            .collect();
+        let mut visited = vec![false; self.heat.len()];
+        let mut clusters = Vec::new();
+        for cell in 0..self.heat.len() {
+            if is_hot[cell] && !visited[cell] {
+                clusters.push(self.collect_cluster(cell, &is_hot, &mut visited));
+            }
+        }
+        let max_cluster_heat = clusters.iter().map(|(_, heat)| *heat).max().unwrap_or(0);
+        return clusters.iter()
+            .filter(|(_, heat)| *heat as f64 >= max_cluster_heat as f64 * MIN_REGION_HEAT_RATIO)
+            .map(|(cells, _)| Bounds {
+                // one cell of padding, since letters on the cell border may have too few points
+                start: Point {
+                    x: max(0, cells.start.x - 1) * CELL_SIZE as i64,
+                    y: max(0, cells.start.y - 1) * CELL_SIZE as i64,
+                },
+                end: Point {
+                    x: min(self.width, (cells.end.x as usize + 2) * CELL_SIZE) as i64 - 1,
+                    y: min(self.height, (cells.end.y as usize + 2) * CELL_SIZE) as i64 - 1,
+                },
+            })
+            .collect();
+    }
+}
+
+/// decode the whole video once to find where the text usually appears, falls
+/// back to the region from params if no text changes were detected at all
+pub fn detect_text_region(path: &str, params: &DetectionParams) -> Result<FrameRegion> {
+    let mut heatmap: Option<TextRegionHeatmap> = None;
+    for_each_frame_change(path, params, |change| {
+        if change.is_text_change {
+            heatmap
+                .get_or_insert_with(|| TextRegionHeatmap::new(change.width, change.height))
+                .add_text_change(&change.text_only_bitmap);
+        }
+        Ok(())
+    })?;
+    let rects = match heatmap {
+        Some(heatmap) => heatmap.infer_regions(),
+        None => Vec::new(),
+    };
+    if rects.is_empty() {
+        return Ok(params.region.clone());
+    }
+    for rect in &rects {
+        eprintln!(
+            "Detected text region at {},{} of size {}x{}",
+            rect.start.x, rect.start.y, rect.get_width(), rect.get_height()
+        );
+    }
+    return Ok(FrameRegion { rects, ..params.region.clone() });
+}
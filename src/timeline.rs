@@ -0,0 +1,142 @@
+//! json file with timestamps of frames that changed noticeably, or of every frame that
+//! differs from the previous one, so that downstream scripts would not have to scrape the stdout
+
+use crate::change_classifier::ChangeKind;
+use crate::detect_still_frames::{DetectionSummary, FrameChange};
+use crate::frame_time::FrameTime;
+use crate::rel_matrix::Bounds;
+use crate::scene_detection::SceneChange;
+use crate::subtitle_events::{EventEnd, SubtitleEvent};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+#[derive(Serialize)]
+pub struct TimelineEntry {
+    pub frame_index: usize,
+    pub pts: Option<i64>,
+    pub millis: u64,
+    pub change_factor: f64,
+    pub real_points_changed: usize,
+    /// how the change was classified by its spread over the frame
+    pub kind: ChangeKind,
+    pub is_text_change: bool,
+    /// box around all changed points, inclusive
+    pub changed_bounds: Option<Bounds>,
+    pub appeared_text_points: usize,
+    pub vanished_text_points: usize,
+    /// set only when waiting for text animation to finish, see settle_frames
+    pub animation_start: Option<FrameTime>,
+    pub animation_end: Option<FrameTime>,
+}
+
+/// time span during which a text was displayed
+#[derive(Serialize)]
+pub struct TimelineEvent {
+    pub start_frame_index: usize,
+    pub start_ms: u64,
+    pub end_frame_index: usize,
+    pub end_ms: u64,
+    pub end_reason: EventEnd,
+}
+
+#[derive(Serialize)]
+pub struct Timeline {
+    pub video: String,
+    /// the first processed frame, set when only a part of the video was processed
+    pub start_frame_index: usize,
+    pub start_ms: u64,
+    /// index and time right after the last processed frame
+    pub frames_total: usize,
+    pub duration_ms: u64,
+    /// regions of interest changes were looked for in, either given or automatically detected
+    pub text_regions: Vec<Bounds>,
+    /// blocks that were learned to blink periodically and excluded from the detection
+    pub blinking_regions: Vec<Bounds>,
+    /// cuts and fades, text changes are not reported in the middle of them
+    pub scene_changes: Vec<SceneChange>,
+    /// every frame that differs from the previous one is listed, otherwise only text changes,
+    /// scene changes and changes classified other than minor, like a blinking cursor
+    pub all_frames: bool,
+    /// frames not listed here are same as the previous listed one, or changed only a bit
+    pub frames: Vec<TimelineEntry>,
+    pub events: Vec<TimelineEvent>,
+}
+
+impl Timeline {
+    pub fn new(video: &str, all_frames: bool) -> Timeline {
+        return Timeline {
+            video: video.to_string(),
+            start_frame_index: 0,
+            start_ms: 0,
+            frames_total: 0,
+            duration_ms: 0,
+            text_regions: Vec::new(),
+            blinking_regions: Vec::new(),
+            scene_changes: Vec::new(),
+            all_frames,
+            frames: Vec::new(),
+            events: Vec::new(),
+        };
+    }
+
+    pub fn add_frame(&mut self, change: &FrameChange) {
+        if let Some(scene_change) = change.scene_change {
+            self.scene_changes.push(scene_change);
+        }
+        let is_notable = change.is_text_change || change.scene_change.is_some() || change.kind != ChangeKind::Minor;
+        if !is_notable && !self.all_frames {
+            return;
+        }
+        self.frames.push(TimelineEntry {
+            frame_index: change.time.frame_index,
+            pts: change.time.pts,
+            millis: change.time.millis,
+            change_factor: change.change_factor,
+            real_points_changed: change.real_points_changed,
+            kind: change.kind,
+            is_text_change: change.is_text_change,
+            changed_bounds: change.changed_bounds,
+            appeared_text_points: change.appeared_text_points,
+            vanished_text_points: change.vanished_text_points,
+            animation_start: change.animation_start,
+            animation_end: change.animation_end,
+        });
+    }
+
+    pub fn add_event<T>(&mut self, event: &SubtitleEvent<T>) {
+        self.events.push(TimelineEvent {
+            start_frame_index: event.start.frame_index,
+            start_ms: event.start.millis,
+            end_frame_index: event.end.frame_index,
+            end_ms: event.end.millis,
+            end_reason: event.end_reason,
+        });
+    }
+
+    pub fn finish(&mut self, summary: &DetectionSummary) {
+        if let Some(start_time) = summary.start_time {
+            self.start_frame_index = start_time.frame_index;
+            self.start_ms = start_time.millis;
+        }
+        self.frames_total = summary.end_time.frame_index;
+        self.duration_ms = summary.end_time.millis;
+        self.blinking_regions = summary.blinking_regions.clone();
+    }
+
+    pub fn save(&self, path: &str) -> std::result::Result<(), std::io::Error> {
+        let json = serde_json::to_string_pretty(self)?;
+        return fs::write(path, json);
+    }
+}
+
+#[derive(Deserialize)]
+struct TimelineRegions {
+    text_regions: Vec<Bounds>,
+}
+
+/// text regions from a previously saved timeline, to be reused as region of interest for OCR
+pub fn load_text_regions(path: &str) -> std::result::Result<Vec<Bounds>, std::io::Error> {
+    let json = fs::read_to_string(path)?;
+    let regions: TimelineRegions = serde_json::from_str(&json)?;
+    return Ok(regions.text_regions);
+}
@@ -0,0 +1,227 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::format::{input, Pixel};
+use ffmpeg::media::Type;
+use ffmpeg::software::scaling::{context::Context, flag::Flags};
+use ffmpeg::util::frame::video::Video;
+use ffmpeg::Rational;
+use std::io;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread::{self, JoinHandle};
+use crate::error::{Error, Result};
+use crate::frame_time::{FrameClock, FrameTime, TimeRange};
+use crate::image_sequence::{is_image_sequence, read_image_sequence, ImageTiming};
+use crate::pipe_input::{is_pipe_input, read_pipe_frames, PipeInput};
+use crate::rgb_frame::{RgbFrame, Rotation};
+
+/// decoded frames waiting for the analysis, decoding pauses when the queue is full,
+/// so that a slow analysis does not make the whole video pile up in memory
+const DECODED_FRAMES_QUEUE: usize = 16;
+
+/// frames per second the stream frame rate may differ from the average one by and still be constant
+const MAX_FRAME_RATE_DEVIATION: f64 = 0.01;
+
+/// which frames of the input to take and how to read them, each field is
+/// used only with the kind of input it is about
+#[derive(Debug, Copy, Clone, Default)]
+pub struct InputParams {
+    /// index of the video stream among all streams of the file, the best one if not set
+    pub stream_index: Option<usize>,
+    /// part of the video to process, frame indices and times stay relative to the file start
+    pub range: TimeRange,
+    /// used when the input is a directory or a glob of images instead of a video file
+    pub image_timing: ImageTiming,
+    /// used when the input is "-", frames are read from stdin then
+    pub pipe: PipeInput,
+    /// frames decoded ahead of the analysis, DECODED_FRAMES_QUEUE if not set
+    pub decode_queue: Option<usize>,
+}
+
+/// size the picture is displayed in: non-square pixels are stretched to square ones, never
+/// squeezed, so that no detail of the letters is lost, like 720x480 with SAR 32:27 -> 853x480
+fn get_display_size(width: u32, height: u32, sample_aspect_ratio: Rational) -> (u32, u32) {
+    let num = sample_aspect_ratio.numerator() as u64;
+    let den = sample_aspect_ratio.denominator() as u64;
+    if sample_aspect_ratio.numerator() <= 0 || sample_aspect_ratio.denominator() <= 0 || num == den {
+        return (width, height);
+    }
+    return if num > den {
+        (((width as u64 * num + den / 2) / den) as u32, height)
+    } else {
+        (width, ((height as u64 * den + num / 2) / num) as u32)
+    };
+}
+
+/// players rotate the picture by the "rotate" tag, phone recordings have it most often
+fn get_rotation(stream: &ffmpeg::format::stream::Stream) -> Rotation {
+    let degrees = stream.metadata()
+        .get("rotate")
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .unwrap_or(0);
+    return match degrees.rem_euclid(360) {
+        90 => Rotation::Clockwise90,
+        180 => Rotation::Clockwise180,
+        270 => Rotation::Clockwise270,
+        _ => Rotation::None,
+    };
+}
+
+/// converts decoded frames to RGB24 in display geometry, so that the OCR
+/// sees letters the same shape as ab_glyph renders them
+struct DisplayScaler {
+    context: Context,
+    /// what the context was made for, it has to be remade when the decoded size changes
+    input: (Pixel, u32, u32),
+    rotation: Rotation,
+}
+
+impl DisplayScaler {
+    fn run(&mut self, time: FrameTime, decoded: &Video) -> Result<RgbFrame> {
+        if self.input != (decoded.format(), decoded.width(), decoded.height()) {
+            eprintln!(
+                "Frame {} at {} ms: frame size changed to {}x{}",
+                time.frame_index, time.millis, decoded.width(), decoded.height()
+            );
+            *self = make_scaler(decoded.format(), decoded.width(), decoded.height(), decoded.aspect_ratio(), self.rotation)?;
+        }
+        let mut rgb_frame = Video::empty();
+        self.context.run(decoded, &mut rgb_frame)?;
+        return Ok(RgbFrame::from_video(time, &rgb_frame).rotate(self.rotation));
+    }
+}
+
+fn make_scaler(
+    format: Pixel,
+    width: u32,
+    height: u32,
+    sample_aspect_ratio: Rational,
+    rotation: Rotation,
+) -> Result<DisplayScaler> {
+    let (display_width, display_height) = get_display_size(width, height, sample_aspect_ratio);
+    let context = Context::get(
+        format,
+        width,
+        height,
+        Pixel::RGB24,
+        display_width,
+        display_height,
+        Flags::BILINEAR,
+    )?;
+    return Ok(DisplayScaler { context, input: (format, width, height), rotation });
+}
+
+/// decode each frame within the range of the chosen video stream of the file to RGB, returns
+/// the moment right after the last decoded frame stops being displayed, when range start is
+/// set, it seeks to the closest key frame before it and drops frames till the start, broken
+/// packets and frames are skipped, on_frame returns false to stop decoding
+pub fn decode_video(
+    path: &str,
+    params: &InputParams,
+    mut on_frame: impl FnMut(RgbFrame) -> bool,
+) -> Result<FrameTime> {
+    ffmpeg::init()?;
+    let range = &params.range;
+
+    let mut ictx = input(&path)?;
+    let input = match params.stream_index {
+        Some(index) => ictx
+            .stream(index)
+            .filter(|stream| stream.codec().medium() == Type::Video)
+            .ok_or_else(|| Error::Input(format!("Stream {} is not a video stream of {}", index, path)))?,
+        None => ictx
+            .streams()
+            .best(Type::Video)
+            .ok_or_else(|| Error::Input(format!("No video stream in {}", path)))?,
+    };
+    let video_stream_index = input.index();
+
+    let mut clock = FrameClock::new(input.time_base(), input.start_time(), input.avg_frame_rate());
+    // r_frame_rate is the rate all timestamps fit in, it is above the average one when frames are not evenly spaced
+    let is_variable_frame_rate = input.avg_frame_rate().numerator() > 0
+        && (f64::from(input.rate()) - f64::from(input.avg_frame_rate())).abs() > MAX_FRAME_RATE_DEVIATION;
+
+    let rotation = get_rotation(&input);
+    let mut decoder = input.codec().decoder().video()?;
+    let mut scaler = make_scaler(decoder.format(), decoder.width(), decoder.height(), decoder.aspect_ratio(), rotation)?;
+
+    if let Some(start_millis) = range.get_seek_millis() {
+        let timestamp = clock.get_seek_timestamp(start_millis);
+        ictx.seek(timestamp, ..timestamp)?;
+        clock.after_seek();
+        if is_variable_frame_rate {
+            eprintln!(
+                "Warning: {} has a variable frame rate, frame indices after the seek to --start are estimated \
+                from the average one and may differ from the ones counted from the file start, \
+                give --start-frame to count them exactly",
+                path
+            );
+        }
+    }
+    let mut end_time = None;
+
+    // returns true once a frame past the range end is reached or on_frame asks to stop
+    let mut receive_and_process_decoded_frames = |decoder: &mut ffmpeg::decoder::Video| -> bool {
+        let mut decoded = Video::empty();
+        while decoder.receive_frame(&mut decoded).is_ok() {
+            let time = clock.next(decoded.timestamp());
+            if range.is_after_end(&time) {
+                return true;
+            }
+            if range.is_before_start(&time) {
+                continue;
+            }
+            match scaler.run(time, &decoded) {
+                Ok(frame) => if !on_frame(frame) {
+                    return true;
+                },
+                Err(error) => eprintln!("Skipping frame {} at {} ms: {}", time.frame_index, time.millis, error),
+            }
+            end_time = Some(clock.end_time());
+        }
+        false
+    };
+
+    let mut is_range_end = false;
+    for (stream, packet) in ictx.packets() {
+        if stream.index() == video_stream_index {
+            if let Err(error) = decoder.send_packet(&packet) {
+                eprintln!("Skipping a broken packet at {:?}: {}", packet.pts(), error);
+                continue;
+            }
+            is_range_end = receive_and_process_decoded_frames(&mut decoder);
+            if is_range_end {
+                break;
+            }
+        }
+    }
+    if !is_range_end {
+        decoder.send_eof()?;
+        receive_and_process_decoded_frames(&mut decoder);
+    }
+
+    Ok(end_time.unwrap_or_else(|| clock.end_time()))
+}
+
+/// decode the video, read the image sequence or frames piped to stdin on a separate thread, frames arrive to the returned
+/// receiver, the receiver gets closed once the video ends or decoding fails, join the thread for the result, dropping the
+/// receiver stops the decoding
+pub fn spawn_decoding(
+    path: &str,
+    params: &InputParams,
+) -> (Receiver<RgbFrame>, JoinHandle<Result<FrameTime>>) {
+    let (frame_sender, frame_receiver) = sync_channel(params.decode_queue.unwrap_or(DECODED_FRAMES_QUEUE));
+    let path = path.to_string();
+    let params = *params;
+    let handle = thread::spawn(move || {
+        // analysis side gone means it failed or panicked, it will be reported on join
+        let on_frame = |frame| frame_sender.send(frame).is_ok();
+        if is_pipe_input(&path) {
+            read_pipe_frames(io::stdin(), &params.pipe, &params.range, on_frame)
+        } else if is_image_sequence(&path) {
+            read_image_sequence(&path, &params.image_timing, &params.range, on_frame)
+        } else {
+            decode_video(&path, &params, on_frame)
+        }
+    });
+    return (frame_receiver, handle);
+}
@@ -0,0 +1,135 @@
+//! chains detect_still_frames and ocr_out_from_image: decodes the video, OCR-s
+//! every frame in which hardsub text changed, without dumping it to a file, and
+//! writes the recognized text as subtitles, each cue lasting till the text gets
+//! cleared or replaced, while the font is being identified frames are kept and
+//! OCR-ed once it is picked
+
+use crate::detect_still_frames::{for_each_frame_change, DetectionParams, FrameChange};
+use crate::error::{Error, Result};
+use crate::font_data::FontData;
+use crate::font_identification::FontSelection;
+use crate::frame_region::FrameRegion;
+use crate::frame_time::FrameTime;
+use crate::ocr_out_from_image::{ocr_frame, SubsOcrFrame};
+use crate::ppm_format;
+use crate::srt_format::{self, SrtCue};
+use crate::subtitle_events::{EventSegmenter, SubtitleEvent};
+use crate::timeline::Timeline;
+use std::fs;
+
+/// None if the frame can not be OCR-ed, it is logged and treated
+/// as one without text, so that the run goes on
+pub fn make_ocr_frame(change: &FrameChange) -> Option<SubsOcrFrame<'static>> {
+    let full_ppm = ppm_format::from_bitmap(change.width, change.height, change.full_bitmap.clone());
+    let text_ppm = ppm_format::from_bitmap(change.width, change.height, change.text_only_bitmap.clone());
+    return match SubsOcrFrame::new(full_ppm, text_ppm) {
+        Ok(ocr_frame_data) => Some(ocr_frame_data),
+        Err(error) => {
+            eprintln!("Skipping OCR of frame {} at {} ms: {}", change.time.frame_index, change.time.millis, error);
+            None
+        },
+    };
+}
+
+/// None if no letters were recognized
+pub fn ocr_lines(ocr_frame_data: &SubsOcrFrame, font_data: &FontData, region: &FrameRegion) -> Option<Vec<String>> {
+    let lines = ocr_frame(ocr_frame_data, font_data, region);
+    return if lines.len() > 0 { Some(lines) } else { None };
+}
+
+/// text of an event, the frame is kept till the font is identified
+enum EventText {
+    Lines(Vec<String>),
+    Pending(FrameTime, SubsOcrFrame<'static>),
+}
+
+fn ocr_and_print(time: FrameTime, ocr_frame_data: &SubsOcrFrame, font_data: &FontData, region: &FrameRegion) -> Option<Vec<String>> {
+    let lines = ocr_lines(ocr_frame_data, font_data, region);
+    println!("Frame {} at {} ms: {:?}", time.frame_index, time.millis, lines.as_deref().unwrap_or(&[]));
+    return lines;
+}
+
+/// None if the frame kept for the OCR turned out to have no text
+fn to_cue(event: SubtitleEvent<EventText>, font_data: &FontData, region: &FrameRegion) -> Option<SubtitleEvent<Vec<String>>> {
+    let lines = match event.payload {
+        EventText::Lines(lines) => lines,
+        EventText::Pending(time, ocr_frame_data) => ocr_and_print(time, &ocr_frame_data, font_data, region)?,
+    };
+    return Some(SubtitleEvent {
+        start: event.start,
+        end: event.end,
+        end_reason: event.end_reason,
+        payload: lines,
+    });
+}
+
+fn event_to_cue(event: SubtitleEvent<Vec<String>>) -> SrtCue {
+    return SrtCue {
+        start_ms: event.start.millis,
+        end_ms: event.end.millis,
+        lines: event.payload,
+    };
+}
+
+/// events closed while the font was being identified are OCR-ed once it is
+fn flush_events(
+    waiting: &mut Vec<SubtitleEvent<EventText>>,
+    font_data: &FontData,
+    region: &FrameRegion,
+    timeline: &mut Timeline,
+    cues: &mut Vec<SrtCue>,
+) {
+    for event in waiting.drain(..) {
+        if let Some(event) = to_cue(event, font_data, region) {
+            timeline.add_event(&event);
+            cues.push(event_to_cue(event));
+        }
+    }
+}
+
+pub fn video_to_srt(
+    path: &str,
+    out_path: &str,
+    timeline_path: Option<&str>,
+    timeline_all_frames: bool,
+    font: &mut FontSelection,
+    params: &DetectionParams,
+) -> Result<()> {
+    let mut cues: Vec<SrtCue> = Vec::new();
+    let mut segmenter = EventSegmenter::new();
+    let mut timeline = Timeline::new(path, timeline_all_frames);
+    timeline.text_regions = params.region.rects.clone();
+
+    let mut waiting: Vec<SubtitleEvent<EventText>> = Vec::new();
+
+    let summary = for_each_frame_change(path, params, |change| {
+        timeline.add_frame(&change);
+        let closed = segmenter.feed(&change, |change| {
+            let ocr_frame_data = make_ocr_frame(change)?;
+            return match font.get_known() {
+                Some(font_data) => ocr_and_print(change.time, &ocr_frame_data, font_data, &params.region)
+                    .map(EventText::Lines),
+                None => {
+                    font.add_frame(&ocr_frame_data, &params.region);
+                    Some(EventText::Pending(change.time, ocr_frame_data))
+                },
+            };
+        });
+        waiting.extend(closed);
+        if let Some(font_data) = font.get_known() {
+            flush_events(&mut waiting, font_data, &params.region, &mut timeline, &mut cues);
+        }
+        Ok(())
+    })?;
+    waiting.extend(segmenter.finish(summary.end_time, summary.get_end_reason()));
+    let font_data = font.finish();
+    flush_events(&mut waiting, font_data, &params.region, &mut timeline, &mut cues);
+
+    if let Some(timeline_path) = timeline_path {
+        timeline.finish(&summary);
+        timeline.save(timeline_path).map_err(Error::io(format!("Failed to write {}", timeline_path)))?;
+    }
+    fs::write(out_path, srt_format::encode(&cues)).map_err(Error::io(format!("Failed to write {}", out_path)))?;
+    println!("Written {} cues to {}", cues.len(), out_path);
+    Ok(())
+}